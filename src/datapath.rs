@@ -1,5 +1,8 @@
 pub trait Datapath {
-    fn execute_instruction(&mut self);
-    fn execute_stage(&mut self);
+    type Error;
+
+    fn execute_instruction(&mut self) -> Result<(), Self::Error>;
+    fn execute_stage(&mut self) -> Result<(), Self::Error>;
     fn get_register(&self, register: &str) -> Option<u64>;
+    fn is_halted(&self) -> bool;
 }
@@ -4,15 +4,15 @@ pub mod mips;
 pub mod tests;
 
 use datapath::Datapath;
+use mips::assembler::assemble;
 use mips::datapath::MipsDatapath;
 use mips::registers::RegisterType;
 
-#[allow(clippy::unusual_byte_groupings)]
 fn main() {
     let mut datapath = MipsDatapath::default();
 
-    let instruction: u32 = 0b000000_01001_01001_01001_00000_100000;
-    datapath.memory.store_word(0, instruction);
+    let program = assemble("add $t1, $t1, $t1", datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
     datapath.registers[RegisterType::T1] = 5;
 
     // println!("{:b}", 2053);
@@ -25,7 +25,9 @@ fn main() {
     println!("{:?}", datapath.memory);
 
     println!("=======================");
-    datapath.execute_instruction();
+    if let Err(error) = datapath.execute_instruction() {
+        println!("{error}");
+    }
     println!("=======================");
 
     // println!("{:#?}", datapath.registers);
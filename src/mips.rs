@@ -1,4 +1,12 @@
+pub mod assembler;
+pub mod console;
 pub mod control_signals;
+pub mod coprocessor0;
 pub mod datapath;
+pub mod device;
+pub mod disassembler;
+pub mod error;
+pub mod fpu;
 pub mod memory;
 pub mod registers;
+pub mod syscall;
@@ -0,0 +1,774 @@
+// Translate MIPS assembly into machine code.
+//
+// Registers may be written by name (`$t0`, as in RegisterType) or by
+// number (`$8`), and floating-point registers as `$f0` to `$f31`. Branch
+// and jump targets are labels, though a branch offset may also be given in
+// instructions and a jump target as an absolute address.
+//
+// Lines hold any number of `label:` definitions followed by an instruction
+// or a directive. The directives are `.text`, `.data`, `.globl`, `.word`,
+// `.half`, `.byte`, `.float`, `.double`, `.ascii`, `.asciiz`, `.space` and
+// `.align`. Data is aligned to its size unless it is a string or space.
+//
+// The pseudo-instructions `li`, `la`, `move`, `not`, `neg`, `mul`, `b`,
+// `beqz`, `bnez`, `blt`, `bgt`, `ble` and `bge` are expanded into real
+// instructions, as are immediate instructions whose immediate is too large.
+//
+// As in MARS, `.macro name (%a, %b)` ... `.end_macro` defines a macro,
+// `.eqv NAME value` a constant, and `.include "file"` inserts a file.
+
+mod diagnostics;
+mod encoder;
+mod lexer;
+mod preprocessor;
+mod pseudo;
+
+use super::{
+    error::ErrorKind,
+    memory::{Endianness, Memory, MemoryConfig},
+};
+pub use diagnostics::Diagnostics;
+use encoder::{check_range, Instruction, Operand, OperandKind};
+use lexer::{Token, TokenKind};
+use preprocessor::Line;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// An assembled program.
+#[derive(Debug)]
+pub struct Program {
+    /// Where the first instruction is placed.
+    pub text_base: u64,
+    /// The machine code, one word per instruction.
+    pub text: Vec<u32>,
+    /// The source line each instruction came from. A pseudo-instruction
+    /// gives several instructions the same line, and an instruction from a
+    /// macro has the line that used the macro.
+    pub lines: Vec<SourceLine>,
+    /// Where the data section is placed.
+    pub data_base: u64,
+    /// The initial contents of the data section, in the configured byte
+    /// order.
+    pub data: Vec<u8>,
+    /// The address of every label.
+    pub symbols: HashMap<String, u64>,
+    /// The labels declared with `.globl`.
+    pub globals: Vec<String>,
+}
+
+impl Program {
+    /// Store the program's machine code and data in memory.
+    pub fn load(&self, memory: &mut Memory) -> Result<(), ErrorKind> {
+        for (i, word) in self.text.iter().enumerate() {
+            memory.store_word(self.text_base + i as u64 * 4, *word)?;
+        }
+        for (i, byte) in self.data.iter().enumerate() {
+            memory.store_byte(self.data_base + i as u64, *byte)?;
+        }
+
+        Ok(())
+    }
+
+    /// The source line of the instruction at an address.
+    pub fn line_at(&self, address: u64) -> Option<&SourceLine> {
+        let offset = address.checked_sub(self.text_base)?;
+        if offset % 4 != 0 {
+            return None;
+        }
+        self.lines.get((offset / 4) as usize)
+    }
+
+    /// The addresses of the instructions generated for a source line.
+    pub fn addresses_of(&self, line: &SourceLine) -> Vec<u64> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, source_line)| *source_line == line)
+            .map(|(i, _)| self.text_base + i as u64 * 4)
+            .collect()
+    }
+}
+
+/// A line in the assembled source, or in a file it includes.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SourceLine {
+    /// The file the line is in, or `None` for source given as a string.
+    pub file: Option<PathBuf>,
+    /// Counting from 1.
+    pub line: usize,
+}
+
+/// The reason a line could not be assembled.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AssemblyErrorKind {
+    UnexpectedCharacter(char),
+    InvalidNumber(String),
+    UnknownMnemonic(String),
+    WrongOperandCount {
+        expected: usize,
+        found: usize,
+    },
+    InvalidOperand {
+        expected: &'static str,
+    },
+    UnknownRegister(String),
+    ImmediateOutOfRange {
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    /// A jump target outside the current 256 MB region, or not aligned.
+    JumpTargetOutOfRange(u64),
+    /// A branch to a label more than 32K instructions away.
+    BranchTargetOutOfRange(String),
+    UnterminatedString,
+    UnknownDirective(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    /// `$at` in a pseudo-instruction that uses it as a scratch register.
+    ReservedRegister,
+    /// An instruction in the data section.
+    InstructionOutsideText(String),
+    /// A data directive in the text section.
+    DataOutsideData(String),
+    /// A memory configuration whose heap starts below its static data.
+    InvalidMemoryLayout,
+    UnreadableFile {
+        path: String,
+        message: String,
+    },
+    /// Macros or included files nested too deeply.
+    RecursionLimit,
+    UnterminatedMacro(String),
+    NestedMacro,
+    UnexpectedEndMacro,
+}
+
+/// An error in the assembly source, and where it was found.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AssemblyError {
+    pub kind: AssemblyErrorKind,
+    /// The file the error is in, or `None` for source given as a string.
+    pub file: Option<PathBuf>,
+    /// Line and column in the source, counting from 1.
+    pub line: usize,
+    pub column: usize,
+    /// The number of characters the error spans, starting at the column.
+    pub length: usize,
+}
+
+impl AssemblyError {
+    fn new(kind: AssemblyErrorKind, location: &SourceLine, column: usize) -> Self {
+        Self {
+            kind,
+            file: location.file.clone(),
+            line: location.line,
+            column,
+            length: 1,
+        }
+    }
+}
+
+impl fmt::Display for AssemblyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblyErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{c}'"),
+            AssemblyErrorKind::InvalidNumber(text) => write!(f, "invalid number '{text}'"),
+            AssemblyErrorKind::UnknownMnemonic(mnemonic) => {
+                write!(f, "unknown mnemonic '{mnemonic}'")
+            }
+            AssemblyErrorKind::WrongOperandCount { expected, found } => {
+                write!(f, "expected {expected} operands, found {found}")
+            }
+            AssemblyErrorKind::InvalidOperand { expected } => write!(f, "expected {expected}"),
+            AssemblyErrorKind::UnknownRegister(name) => write!(f, "unknown register '${name}'"),
+            AssemblyErrorKind::ImmediateOutOfRange { value, min, max } => {
+                write!(f, "immediate {value} is out of range {min} to {max}")
+            }
+            AssemblyErrorKind::JumpTargetOutOfRange(target) => {
+                write!(f, "jump target {target:#010x} is out of range")
+            }
+            AssemblyErrorKind::BranchTargetOutOfRange(label) => {
+                write!(f, "branch target '{label}' is too far away")
+            }
+            AssemblyErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            AssemblyErrorKind::UnknownDirective(name) => write!(f, "unknown directive '{name}'"),
+            AssemblyErrorKind::UndefinedLabel(label) => write!(f, "undefined label '{label}'"),
+            AssemblyErrorKind::DuplicateLabel(label) => {
+                write!(f, "label '{label}' is already defined")
+            }
+            AssemblyErrorKind::ReservedRegister => {
+                write!(f, "$at is reserved for the assembler in this instruction")
+            }
+            AssemblyErrorKind::InstructionOutsideText(mnemonic) => {
+                write!(f, "instruction '{mnemonic}' outside the text section")
+            }
+            AssemblyErrorKind::DataOutsideData(name) => {
+                write!(f, "directive '{name}' outside the data section")
+            }
+            AssemblyErrorKind::InvalidMemoryLayout => {
+                write!(f, "the heap starts below the static data segment")
+            }
+            AssemblyErrorKind::UnreadableFile { path, message } => {
+                write!(f, "cannot read '{path}': {message}")
+            }
+            AssemblyErrorKind::RecursionLimit => write!(
+                f,
+                "macros or includes nested more than {} deep",
+                preprocessor::MAX_DEPTH
+            ),
+            AssemblyErrorKind::UnterminatedMacro(name) => {
+                write!(f, "macro '{name}' has no .end_macro")
+            }
+            AssemblyErrorKind::NestedMacro => write!(f, "macros cannot be defined inside macros"),
+            AssemblyErrorKind::UnexpectedEndMacro => write!(f, ".end_macro outside a macro"),
+        }
+    }
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// Assemble a program, placing its text at the start of the text segment
+/// and its data at the start of the static data segment. Every error in
+/// the program is reported, not just the first.
+///
+/// Included files are found relative to the current directory.
+pub fn assemble(source: &str, config: &MemoryConfig) -> Result<Program, Diagnostics> {
+    let (lines, diagnostics) = preprocessor::preprocess(source, None);
+    assemble_lines(lines, diagnostics, config)
+}
+
+/// Assemble a program read from a file. Included files are found relative
+/// to the file's directory.
+pub fn assemble_file(path: &Path, config: &MemoryConfig) -> Result<Program, Diagnostics> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(io_error) => {
+            let mut diagnostics = Diagnostics::default();
+            diagnostics.report(AssemblyError {
+                kind: AssemblyErrorKind::UnreadableFile {
+                    path: path.display().to_string(),
+                    message: io_error.to_string(),
+                },
+                file: None,
+                line: 0,
+                column: 0,
+                length: 0,
+            });
+            return Err(diagnostics);
+        }
+    };
+
+    let (lines, diagnostics) = preprocessor::preprocess(&source, Some(path));
+    assemble_lines(lines, diagnostics, config)
+}
+
+fn assemble_lines(
+    lines: Vec<Line>,
+    diagnostics: Diagnostics,
+    config: &MemoryConfig,
+) -> Result<Program, Diagnostics> {
+    let mut assembler = Assembler::new(config, diagnostics);
+
+    for line in lines {
+        if let Err((column, kind)) = assembler.line(&line) {
+            assembler.report(kind, &line.location, column);
+        }
+    }
+
+    assembler.finish()
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Section {
+    Text,
+    Data,
+}
+
+// An instruction waiting for every label to be known.
+struct Statement {
+    location: SourceLine,
+    origin: SourceLine,
+    column: usize,
+    mnemonic: String,
+    operands: Vec<Operand>,
+    address: u64,
+}
+
+// A data word to be filled with the address of a label.
+struct Fixup {
+    offset: usize,
+    label: String,
+    location: SourceLine,
+    column: usize,
+}
+
+// The first pass places instructions and data and records labels; the
+// second, in `finish`, encodes instructions once every label is known.
+struct Assembler<'a> {
+    config: &'a MemoryConfig,
+    section: Section,
+    statements: Vec<Statement>,
+    data: Vec<u8>,
+    fixups: Vec<Fixup>,
+    symbols: HashMap<String, u64>,
+    globals: Vec<(String, SourceLine, usize)>,
+    // Labels are bound to the next item, once it has been aligned.
+    pending_labels: Vec<String>,
+    diagnostics: Diagnostics,
+}
+
+type LineResult<T> = Result<T, (usize, AssemblyErrorKind)>;
+
+impl<'a> Assembler<'a> {
+    fn new(config: &'a MemoryConfig, diagnostics: Diagnostics) -> Self {
+        Self {
+            config,
+            section: Section::Text,
+            statements: Vec::new(),
+            data: Vec::new(),
+            fixups: Vec::new(),
+            symbols: HashMap::new(),
+            globals: Vec::new(),
+            pending_labels: Vec::new(),
+            diagnostics,
+        }
+    }
+
+    fn report(&mut self, kind: AssemblyErrorKind, location: &SourceLine, column: usize) {
+        self.diagnostics
+            .report(AssemblyError::new(kind, location, column));
+    }
+
+    fn line(&mut self, line: &Line) -> LineResult<()> {
+        let mut tokens = line.tokens.as_slice();
+
+        while let [Token {
+            kind: TokenKind::Identifier(name),
+            column,
+            ..
+        }, Token {
+            kind: TokenKind::Colon,
+            ..
+        }, rest @ ..] = tokens
+        {
+            // A duplicate label does not stop the rest of the line being
+            // checked.
+            if let Err((column, kind)) = self.define(name, *column) {
+                self.report(kind, &line.location, column);
+            }
+            tokens = rest;
+        }
+
+        let Some((first, rest)) = tokens.split_first() else {
+            return Ok(());
+        };
+        let TokenKind::Identifier(name) = &first.kind else {
+            return Err((
+                first.column,
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "a mnemonic",
+                },
+            ));
+        };
+        let operands = parse_operands(rest)?;
+
+        if name.starts_with('.') {
+            return self.directive(name, first.column, &operands, &line.location);
+        }
+
+        if self.section != Section::Text {
+            return Err((
+                first.column,
+                AssemblyErrorKind::InstructionOutsideText(name.clone()),
+            ));
+        }
+        let mnemonic = name.to_ascii_lowercase();
+        let instructions = match pseudo::expand(&mnemonic, first.column, &operands)? {
+            Some(expansion) => expansion,
+            None => vec![(mnemonic, operands)],
+        };
+
+        self.bind_labels(self.text_address());
+        for (mnemonic, operands) in instructions {
+            self.statements.push(Statement {
+                location: line.location.clone(),
+                origin: line.origin.clone(),
+                column: first.column,
+                mnemonic,
+                operands,
+                address: self.text_address(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, column: usize) -> LineResult<()> {
+        if self.symbols.contains_key(name) || self.pending_labels.iter().any(|label| label == name)
+        {
+            return Err((column, AssemblyErrorKind::DuplicateLabel(name.to_string())));
+        }
+
+        self.pending_labels.push(name.to_string());
+        Ok(())
+    }
+
+    fn bind_labels(&mut self, address: u64) {
+        for label in self.pending_labels.drain(..) {
+            self.symbols.insert(label, address);
+        }
+    }
+
+    fn text_address(&self) -> u64 {
+        self.config.text_base + self.statements.len() as u64 * 4
+    }
+
+    fn data_address(&self) -> u64 {
+        self.config.static_data_base + self.data.len() as u64
+    }
+
+    fn current_address(&self) -> u64 {
+        match self.section {
+            Section::Text => self.text_address(),
+            Section::Data => self.data_address(),
+        }
+    }
+
+    fn directive(
+        &mut self,
+        name: &str,
+        column: usize,
+        operands: &[Operand],
+        location: &SourceLine,
+    ) -> LineResult<()> {
+        let expect = |count: usize| {
+            if operands.len() != count {
+                return Err((
+                    column,
+                    AssemblyErrorKind::WrongOperandCount {
+                        expected: count,
+                        found: operands.len(),
+                    },
+                ));
+            }
+            Ok(())
+        };
+        let at_least_one = || {
+            if operands.is_empty() {
+                return Err((
+                    column,
+                    AssemblyErrorKind::WrongOperandCount {
+                        expected: 1,
+                        found: 0,
+                    },
+                ));
+            }
+            Ok(())
+        };
+
+        match name {
+            ".text" | ".data" => {
+                expect(0)?;
+                self.bind_labels(self.current_address());
+                self.section = if name == ".text" {
+                    Section::Text
+                } else {
+                    Section::Data
+                };
+                return Ok(());
+            }
+            ".globl" => {
+                at_least_one()?;
+                for operand in operands {
+                    let OperandKind::Label(label) = &operand.kind else {
+                        return Err((
+                            operand.column,
+                            AssemblyErrorKind::InvalidOperand {
+                                expected: "a label",
+                            },
+                        ));
+                    };
+                    self.globals
+                        .push((label.clone(), location.clone(), operand.column));
+                }
+                return Ok(());
+            }
+            ".word" | ".half" | ".byte" | ".ascii" | ".asciiz" | ".space" | ".align" | ".float"
+            | ".double" => (),
+            _ => {
+                return Err((
+                    column,
+                    AssemblyErrorKind::UnknownDirective(name.to_string()),
+                ))
+            }
+        }
+
+        if self.section != Section::Data {
+            return Err((column, AssemblyErrorKind::DataOutsideData(name.to_string())));
+        }
+
+        match name {
+            ".word" | ".half" | ".byte" => {
+                at_least_one()?;
+                let (size, min, max) = match name {
+                    ".word" => (4, i32::MIN as i64, u32::MAX as i64),
+                    ".half" => (2, i16::MIN as i64, u16::MAX as i64),
+                    _ => (1, i8::MIN as i64, u8::MAX as i64),
+                };
+                self.align(size);
+                for operand in operands {
+                    match &operand.kind {
+                        OperandKind::Immediate(value) => {
+                            check_range(*value, min, max, operand.column)?;
+                            self.emit(*value as u64, size);
+                        }
+                        // Only words are wide enough to hold an address.
+                        OperandKind::Label(label) if size == 4 => {
+                            self.fixups.push(Fixup {
+                                offset: self.data.len(),
+                                label: label.clone(),
+                                location: location.clone(),
+                                column: operand.column,
+                            });
+                            self.emit(0, size);
+                        }
+                        _ => return Err(invalid(operand, "an integer")),
+                    }
+                }
+            }
+            ".float" | ".double" => {
+                at_least_one()?;
+                let size = if name == ".float" { 4 } else { 8 };
+                self.align(size);
+                for operand in operands {
+                    let value = match operand.kind {
+                        OperandKind::Float(value) => value,
+                        OperandKind::Immediate(value) => value as f64,
+                        _ => return Err(invalid(operand, "a number")),
+                    };
+                    let bits = match size {
+                        4 => (value as f32).to_bits() as u64,
+                        _ => value.to_bits(),
+                    };
+                    self.emit(bits, size);
+                }
+            }
+            ".ascii" | ".asciiz" => {
+                at_least_one()?;
+                self.align(1);
+                for operand in operands {
+                    let OperandKind::String(string) = &operand.kind else {
+                        return Err(invalid(operand, "a string"));
+                    };
+                    self.data.extend_from_slice(string.as_bytes());
+                    if name == ".asciiz" {
+                        self.data.push(0);
+                    }
+                }
+            }
+            ".space" => {
+                expect(1)?;
+                // The static data ends where the heap begins.
+                let max = self
+                    .config
+                    .heap_base
+                    .checked_sub(self.config.static_data_base)
+                    .ok_or((column, AssemblyErrorKind::InvalidMemoryLayout))?;
+                let max = max.min(i64::MAX as u64) as i64;
+                let size = immediate(&operands[0], 0, max)?;
+                self.align(1);
+                self.data.resize(self.data.len() + size as usize, 0);
+            }
+            _ => {
+                expect(1)?;
+                let power = immediate(&operands[0], 0, 3)?;
+                self.align(1 << power);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Pad the data to a multiple of `size` bytes, and bind any waiting
+    // labels to the aligned address.
+    fn align(&mut self, size: usize) {
+        let padding = (size - self.data.len() % size) % size;
+        self.data.resize(self.data.len() + padding, 0);
+        self.bind_labels(self.data_address());
+    }
+
+    // Append the low `size` bytes of a value, in the configured byte order.
+    fn emit(&mut self, value: u64, size: usize) {
+        match self.config.endianness {
+            Endianness::Big => self
+                .data
+                .extend_from_slice(&value.to_be_bytes()[8 - size..]),
+            Endianness::Little => self.data.extend_from_slice(&value.to_le_bytes()[..size]),
+        }
+    }
+
+    fn finish(mut self) -> Result<Program, Diagnostics> {
+        self.bind_labels(self.current_address());
+
+        for (label, location, column) in &self.globals {
+            if !self.symbols.contains_key(label) {
+                self.diagnostics.report(AssemblyError::new(
+                    AssemblyErrorKind::UndefinedLabel(label.clone()),
+                    location,
+                    *column,
+                ));
+            }
+        }
+
+        let lines = self
+            .statements
+            .iter()
+            .map(|statement| statement.origin.clone())
+            .collect();
+        let mut text = Vec::with_capacity(self.statements.len());
+        for statement in &self.statements {
+            let instruction = Instruction {
+                mnemonic: &statement.mnemonic,
+                column: statement.column,
+                operands: &statement.operands,
+                symbols: &self.symbols,
+            };
+            match encoder::encode(&instruction, statement.address) {
+                Ok(word) => text.push(word),
+                Err((column, kind)) => {
+                    self.diagnostics
+                        .report(AssemblyError::new(kind, &statement.location, column));
+                    text.push(0);
+                }
+            }
+        }
+
+        for fixup in &self.fixups {
+            let Some(&address) = self.symbols.get(&fixup.label) else {
+                self.diagnostics.report(AssemblyError::new(
+                    AssemblyErrorKind::UndefinedLabel(fixup.label.clone()),
+                    &fixup.location,
+                    fixup.column,
+                ));
+                continue;
+            };
+            let bytes = match self.config.endianness {
+                Endianness::Big => (address as u32).to_be_bytes(),
+                Endianness::Little => (address as u32).to_le_bytes(),
+            };
+            self.data[fixup.offset..fixup.offset + 4].copy_from_slice(&bytes);
+        }
+
+        if !self.diagnostics.is_empty() {
+            self.diagnostics
+                .errors
+                .sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+            return Err(self.diagnostics);
+        }
+
+        Ok(Program {
+            text_base: self.config.text_base,
+            text,
+            lines,
+            data_base: self.config.static_data_base,
+            data: self.data,
+            symbols: self.symbols,
+            globals: self.globals.into_iter().map(|(label, ..)| label).collect(),
+        })
+    }
+}
+
+fn invalid(operand: &Operand, expected: &'static str) -> (usize, AssemblyErrorKind) {
+    (
+        operand.column,
+        AssemblyErrorKind::InvalidOperand { expected },
+    )
+}
+
+fn immediate(operand: &Operand, min: i64, max: i64) -> LineResult<i64> {
+    match operand.kind {
+        OperandKind::Immediate(value) => check_range(value, min, max, operand.column),
+        _ => Err(invalid(operand, "an integer")),
+    }
+}
+
+/// Split comma-separated tokens into operands.
+fn parse_operands(tokens: &[Token]) -> Result<Vec<Operand>, (usize, AssemblyErrorKind)> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let commas: Vec<usize> = tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Comma)
+        .map(|token| token.column)
+        .collect();
+
+    // A missing operand is reported at the comma before it, or the one after
+    // it when it comes first.
+    tokens
+        .split(|token| token.kind == TokenKind::Comma)
+        .enumerate()
+        .map(|(index, operand)| {
+            let comma = match index {
+                0 => commas.first(),
+                _ => commas.get(index - 1),
+            };
+            parse_operand(operand, comma.copied().unwrap_or_default())
+        })
+        .collect()
+}
+
+fn parse_operand(tokens: &[Token], comma: usize) -> Result<Operand, (usize, AssemblyErrorKind)> {
+    use TokenKind::*;
+
+    let Some(first) = tokens.first() else {
+        return Err((
+            comma,
+            AssemblyErrorKind::InvalidOperand {
+                expected: "an operand",
+            },
+        ));
+    };
+    let kinds: Vec<&TokenKind> = tokens.iter().map(|token| &token.kind).collect();
+
+    let kind = match kinds.as_slice() {
+        [Register(name)] => OperandKind::Register(name.clone()),
+        [Integer(value)] => OperandKind::Immediate(*value),
+        [Float(value)] => OperandKind::Float(*value),
+        [String(string)] => OperandKind::String(string.clone()),
+        [Identifier(name)] => OperandKind::Label(name.clone()),
+        [LeftParen, Register(base), RightParen] => OperandKind::Memory {
+            offset: 0,
+            base: base.clone(),
+        },
+        [Integer(offset), LeftParen, Register(base), RightParen] => OperandKind::Memory {
+            offset: *offset,
+            base: base.clone(),
+        },
+        _ => {
+            return Err((
+                first.column,
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "a register, immediate, label or memory operand",
+                },
+            ))
+        }
+    };
+
+    Ok(Operand {
+        kind,
+        column: first.column,
+    })
+}
@@ -0,0 +1,79 @@
+use super::{lexer, AssemblyError};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Every error found while assembling a program, with the source text
+/// needed to show where each one is.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// In the order they were found.
+    pub errors: Vec<AssemblyError>,
+    // The text of each file read, keyed by path, or `None` for source given
+    // as a string.
+    sources: HashMap<Option<PathBuf>, String>,
+}
+
+impl Diagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub(super) fn add_source(&mut self, file: Option<&Path>, source: &str) {
+        self.sources
+            .insert(file.map(Path::to_path_buf), source.to_string());
+    }
+
+    /// Record an error, widening it to cover the token it points at.
+    pub(super) fn report(&mut self, mut error: AssemblyError) {
+        if let Some(text) = self.source_line(&error) {
+            if let Ok(tokens) = lexer::tokenize(text) {
+                if let Some(token) = tokens.iter().find(|token| token.column == error.column) {
+                    error.length = token.length;
+                }
+            }
+        }
+
+        self.errors.push(error);
+    }
+
+    fn source_line(&self, error: &AssemblyError) -> Option<&str> {
+        let source = self.sources.get(&error.file)?;
+        source.lines().nth(error.line.checked_sub(1)?)
+    }
+
+    /// Every error with an excerpt of its line, the span underlined with
+    /// carets.
+    pub fn render(&self) -> String {
+        let mut rendered = String::new();
+
+        for error in &self.errors {
+            rendered += &format!("{error}\n");
+            let Some(text) = self.source_line(error) else {
+                continue;
+            };
+
+            // Carets line up under tabs as well as spaces.
+            let indent: String = text
+                .chars()
+                .take(error.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let gutter = " ".repeat(error.line.to_string().len());
+
+            rendered += &format!("{gutter} |\n");
+            rendered += &format!("{} | {text}\n", error.line);
+            rendered += &format!("{gutter} | {indent}{}\n", "^".repeat(error.length.max(1)));
+        }
+
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
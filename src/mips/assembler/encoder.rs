@@ -0,0 +1,537 @@
+use super::AssemblyErrorKind;
+use crate::mips::fpu::{CONDITIONS, FMT_DOUBLE, FMT_SINGLE, FMT_WORD};
+use crate::mips::registers::GPR_NAMES;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperandKind {
+    /// A register, without the leading `$`.
+    Register(String),
+    Immediate(i64),
+    Float(f64),
+    String(String),
+    /// A reference to a label, resolved once every label is known.
+    Label(String),
+    /// The upper or lower 16 bits of a label's address, as used by the
+    /// expansion of `la`.
+    UpperHalf(String),
+    LowerHalf(String),
+    /// A base register and offset, written `offset($base)`.
+    Memory {
+        offset: i64,
+        base: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    pub column: usize,
+}
+
+/// A single machine instruction, ready to be encoded.
+pub struct Instruction<'a> {
+    pub mnemonic: &'a str,
+    pub column: usize,
+    pub operands: &'a [Operand],
+    /// The addresses of the program's labels.
+    pub symbols: &'a HashMap<String, u64>,
+}
+
+type Result<T> = std::result::Result<T, (usize, AssemblyErrorKind)>;
+
+impl Instruction<'_> {
+    fn expect(&self, count: usize) -> Result<()> {
+        if self.operands.len() != count {
+            return Err((
+                self.column,
+                AssemblyErrorKind::WrongOperandCount {
+                    expected: count,
+                    found: self.operands.len(),
+                },
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn register(&self, index: usize) -> Result<(&str, usize)> {
+        let operand = &self.operands[index];
+        match &operand.kind {
+            OperandKind::Register(name) => Ok((name, operand.column)),
+            _ => Err((
+                operand.column,
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "a register",
+                },
+            )),
+        }
+    }
+
+    fn gpr(&self, index: usize) -> Result<u32> {
+        let (name, column) = self.register(index)?;
+        gpr_number(name).ok_or((column, AssemblyErrorKind::UnknownRegister(name.to_string())))
+    }
+
+    fn fpr(&self, index: usize) -> Result<u32> {
+        let (name, column) = self.register(index)?;
+        fpr_number(name).ok_or((column, AssemblyErrorKind::UnknownRegister(name.to_string())))
+    }
+
+    // Coprocessor registers other than the FPRs are written by number.
+    fn numbered(&self, index: usize) -> Result<u32> {
+        let (name, column) = self.register(index)?;
+        match name.parse::<u32>() {
+            Ok(number) if number < 32 => Ok(number),
+            _ => Err((column, AssemblyErrorKind::UnknownRegister(name.to_string()))),
+        }
+    }
+
+    fn immediate(&self, index: usize, min: i64, max: i64) -> Result<i64> {
+        let operand = &self.operands[index];
+        match &operand.kind {
+            OperandKind::Immediate(value) => check_range(*value, min, max, operand.column),
+            OperandKind::UpperHalf(name) => {
+                let address = self.label(name, operand.column)?;
+                check_range(((address >> 16) & 0xFFFF) as i64, min, max, operand.column)
+            }
+            OperandKind::LowerHalf(name) => {
+                let address = self.label(name, operand.column)?;
+                check_range((address & 0xFFFF) as i64, min, max, operand.column)
+            }
+            _ => Err((
+                operand.column,
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "an immediate",
+                },
+            )),
+        }
+    }
+
+    // Returns the 16-bit offset and the base register.
+    fn memory(&self, index: usize) -> Result<(u32, u32)> {
+        let operand = &self.operands[index];
+        let OperandKind::Memory { offset, base } = &operand.kind else {
+            return Err((
+                operand.column,
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "a memory operand",
+                },
+            ));
+        };
+
+        let offset = check_range(*offset, -0x8000, 0x7FFF, operand.column)?;
+        let base = gpr_number(base).ok_or((
+            operand.column,
+            AssemblyErrorKind::UnknownRegister(base.clone()),
+        ))?;
+        Ok((offset as u16 as u32, base))
+    }
+
+    fn label(&self, name: &str, column: usize) -> Result<u64> {
+        self.symbols
+            .get(name)
+            .copied()
+            .ok_or((column, AssemblyErrorKind::UndefinedLabel(name.to_string())))
+    }
+
+    // Branch offsets are counted in instructions from the one after the
+    // branch, and may be given directly or as a label.
+    fn branch_offset(&self, index: usize, address: u64) -> Result<u32> {
+        let operand = &self.operands[index];
+        let OperandKind::Label(name) = &operand.kind else {
+            return Ok(self.immediate(index, -0x8000, 0x7FFF)? as u16 as u32);
+        };
+
+        let target = self.label(name, operand.column)?;
+        let offset = (target as i64 - (address as i64 + 4)) >> 2;
+        if !(-0x8000..=0x7FFF).contains(&offset) {
+            return Err((
+                operand.column,
+                AssemblyErrorKind::BranchTargetOutOfRange(name.clone()),
+            ));
+        }
+
+        Ok(offset as u16 as u32)
+    }
+
+    // Jumps stay within the 256 MB region of the instruction after them.
+    fn jump_target(&self, index: usize, address: u64) -> Result<u32> {
+        let operand = &self.operands[index];
+        let column = operand.column;
+        let target = match &operand.kind {
+            OperandKind::Label(name) => self.label(name, column)?,
+            _ => self.immediate(index, 0, u32::MAX as i64)? as u64,
+        };
+        let region = (address + 4) & 0xF000_0000;
+
+        if target & 0xF000_0000 != region || !target.is_multiple_of(4) {
+            return Err((column, AssemblyErrorKind::JumpTargetOutOfRange(target)));
+        }
+
+        Ok((target as u32 >> 2) & 0x03FF_FFFF)
+    }
+
+    // The optional condition code operand of bc1t, bc1f and c.cond.fmt.
+    fn condition_code(&self, index: usize) -> Result<u32> {
+        Ok(self.immediate(index, 0, 7)? as u32)
+    }
+}
+
+/// Encode an instruction placed at the given address.
+pub fn encode(instruction: &Instruction, address: u64) -> Result<u32> {
+    let i = instruction;
+
+    let word = match i.mnemonic {
+        "nop" => {
+            i.expect(0)?;
+            0
+        }
+        "syscall" | "break" => {
+            i.expect(0)?;
+            let funct = if i.mnemonic == "syscall" { 0x0C } else { 0x0D };
+            r_type(0, 0, 0, 0, funct)
+        }
+        "eret" => {
+            i.expect(0)?;
+            0x4200_0018
+        }
+
+        "add" | "addu" | "sub" | "subu" | "and" | "or" | "xor" | "nor" | "slt" | "sltu" => {
+            let funct = match i.mnemonic {
+                "add" => 0x20,
+                "addu" => 0x21,
+                "sub" => 0x22,
+                "subu" => 0x23,
+                "and" => 0x24,
+                "or" => 0x25,
+                "xor" => 0x26,
+                "nor" => 0x27,
+                "slt" => 0x2A,
+                _ => 0x2B,
+            };
+            i.expect(3)?;
+            r_type(i.gpr(1)?, i.gpr(2)?, i.gpr(0)?, 0, funct)
+        }
+        "sll" | "srl" | "sra" => {
+            let funct = match i.mnemonic {
+                "sll" => 0x00,
+                "srl" => 0x02,
+                _ => 0x03,
+            };
+            i.expect(3)?;
+            let shamt = i.immediate(2, 0, 31)? as u32;
+            r_type(0, i.gpr(1)?, i.gpr(0)?, shamt, funct)
+        }
+        "sllv" | "srlv" | "srav" => {
+            let funct = match i.mnemonic {
+                "sllv" => 0x04,
+                "srlv" => 0x06,
+                _ => 0x07,
+            };
+            i.expect(3)?;
+            r_type(i.gpr(2)?, i.gpr(1)?, i.gpr(0)?, 0, funct)
+        }
+        "jr" => {
+            i.expect(1)?;
+            r_type(i.gpr(0)?, 0, 0, 0, 0x08)
+        }
+        // The link register defaults to $ra.
+        "jalr" if i.operands.len() == 1 => r_type(i.gpr(0)?, 0, 31, 0, 0x09),
+        "jalr" => {
+            i.expect(2)?;
+            r_type(i.gpr(1)?, 0, i.gpr(0)?, 0, 0x09)
+        }
+        "mfhi" | "mflo" => {
+            i.expect(1)?;
+            let funct = if i.mnemonic == "mfhi" { 0x10 } else { 0x12 };
+            r_type(0, 0, i.gpr(0)?, 0, funct)
+        }
+        "mthi" | "mtlo" => {
+            i.expect(1)?;
+            let funct = if i.mnemonic == "mthi" { 0x11 } else { 0x13 };
+            r_type(i.gpr(0)?, 0, 0, 0, funct)
+        }
+        "mult" | "multu" | "div" | "divu" => {
+            let funct = match i.mnemonic {
+                "mult" => 0x18,
+                "multu" => 0x19,
+                "div" => 0x1A,
+                _ => 0x1B,
+            };
+            i.expect(2)?;
+            r_type(i.gpr(0)?, i.gpr(1)?, 0, 0, funct)
+        }
+
+        "addi" | "addiu" | "slti" | "sltiu" => {
+            let opcode = match i.mnemonic {
+                "addi" => 8,
+                "addiu" => 9,
+                "slti" => 10,
+                _ => 11,
+            };
+            i.expect(3)?;
+            let immediate = i.immediate(2, -0x8000, 0x7FFF)?;
+            i_type(opcode, i.gpr(1)?, i.gpr(0)?, immediate as u16 as u32)
+        }
+        // The logical immediates are zero-extended.
+        "andi" | "ori" | "xori" => {
+            let opcode = match i.mnemonic {
+                "andi" => 12,
+                "ori" => 13,
+                _ => 14,
+            };
+            i.expect(3)?;
+            let immediate = i.immediate(2, 0, 0xFFFF)?;
+            i_type(opcode, i.gpr(1)?, i.gpr(0)?, immediate as u32)
+        }
+        "lui" => {
+            i.expect(2)?;
+            let immediate = i.immediate(1, -0x8000, 0xFFFF)?;
+            i_type(15, 0, i.gpr(0)?, immediate as u16 as u32)
+        }
+
+        "lb" | "lh" | "lw" | "lbu" | "lhu" | "sb" | "sh" | "sw" => {
+            let opcode = match i.mnemonic {
+                "lb" => 32,
+                "lh" => 33,
+                "lw" => 35,
+                "lbu" => 36,
+                "lhu" => 37,
+                "sb" => 40,
+                "sh" => 41,
+                _ => 43,
+            };
+            i.expect(2)?;
+            let (offset, base) = i.memory(1)?;
+            i_type(opcode, base, i.gpr(0)?, offset)
+        }
+        "lwc1" | "ldc1" | "swc1" | "sdc1" => {
+            let opcode = match i.mnemonic {
+                "lwc1" => 49,
+                "ldc1" => 53,
+                "swc1" => 57,
+                _ => 61,
+            };
+            i.expect(2)?;
+            let (offset, base) = i.memory(1)?;
+            i_type(opcode, base, i.fpr(0)?, offset)
+        }
+
+        "beq" | "bne" => {
+            i.expect(3)?;
+            let opcode = if i.mnemonic == "beq" { 4 } else { 5 };
+            i_type(opcode, i.gpr(0)?, i.gpr(1)?, i.branch_offset(2, address)?)
+        }
+        "blez" | "bgtz" => {
+            i.expect(2)?;
+            let opcode = if i.mnemonic == "blez" { 6 } else { 7 };
+            i_type(opcode, i.gpr(0)?, 0, i.branch_offset(1, address)?)
+        }
+        "bltz" | "bgez" | "bltzal" | "bgezal" => {
+            let rt = match i.mnemonic {
+                "bltz" => 0b00000,
+                "bgez" => 0b00001,
+                "bltzal" => 0b10000,
+                _ => 0b10001,
+            };
+            i.expect(2)?;
+            i_type(1, i.gpr(0)?, rt, i.branch_offset(1, address)?)
+        }
+        "j" | "jal" => {
+            i.expect(1)?;
+            let opcode = if i.mnemonic == "j" { 2 } else { 3 };
+            (opcode << 26) | i.jump_target(0, address)?
+        }
+
+        "mfc0" | "mtc0" => {
+            i.expect(2)?;
+            let rs = if i.mnemonic == "mfc0" {
+                0b00000
+            } else {
+                0b00100
+            };
+            cop_move(16, rs, i.gpr(0)?, i.numbered(1)?)
+        }
+        "mfc1" | "mtc1" => {
+            i.expect(2)?;
+            let rs = if i.mnemonic == "mfc1" {
+                0b00000
+            } else {
+                0b00100
+            };
+            cop_move(17, rs, i.gpr(0)?, i.fpr(1)?)
+        }
+        "cfc1" | "ctc1" => {
+            i.expect(2)?;
+            let rs = if i.mnemonic == "cfc1" {
+                0b00010
+            } else {
+                0b00110
+            };
+            cop_move(17, rs, i.gpr(0)?, i.numbered(1)?)
+        }
+        // The condition code defaults to 0.
+        "bc1f" | "bc1t" => {
+            let tf = if i.mnemonic == "bc1t" { 1 } else { 0 };
+            let cc = match i.operands.len() {
+                1 => 0,
+                _ => {
+                    i.expect(2)?;
+                    i.condition_code(0)?
+                }
+            };
+            let offset = i.branch_offset(i.operands.len() - 1, address)?;
+            i_type(17, 0b01000, (cc << 2) | tf, offset)
+        }
+
+        mnemonic => return encode_floating_point(i, mnemonic),
+    };
+
+    Ok(word)
+}
+
+// Floating-point arithmetic, conversions, compares and moves, whose
+// mnemonics end in one or two formats, such as add.s or cvt.d.w.
+fn encode_floating_point(i: &Instruction, mnemonic: &str) -> Result<u32> {
+    let unknown = || {
+        (
+            i.column,
+            AssemblyErrorKind::UnknownMnemonic(mnemonic.to_string()),
+        )
+    };
+
+    let (operation, fmt) = mnemonic.rsplit_once('.').ok_or_else(unknown)?;
+    let fmt = match fmt {
+        "s" => FMT_SINGLE,
+        "d" => FMT_DOUBLE,
+        "w" => FMT_WORD,
+        _ => return Err(unknown()),
+    };
+
+    let (funct, shape) = match operation {
+        "add" => (0x00, Shape::Binary),
+        "sub" => (0x01, Shape::Binary),
+        "mul" => (0x02, Shape::Binary),
+        "div" => (0x03, Shape::Binary),
+        "sqrt" => (0x04, Shape::Unary),
+        "abs" => (0x05, Shape::Unary),
+        "mov" => (0x06, Shape::Unary),
+        "neg" => (0x07, Shape::Unary),
+        "round.w" => (0x0C, Shape::Unary),
+        "trunc.w" => (0x0D, Shape::Unary),
+        "ceil.w" => (0x0E, Shape::Unary),
+        "floor.w" => (0x0F, Shape::Unary),
+        "movf" | "movt" => (0x11, Shape::ConditionalOnCode),
+        "movz" => (0x12, Shape::ConditionalOnRegister),
+        "movn" => (0x13, Shape::ConditionalOnRegister),
+        "cvt.s" => (0x20, Shape::Unary),
+        "cvt.d" => (0x21, Shape::Unary),
+        "cvt.w" => (0x24, Shape::Unary),
+        _ => match operation.strip_prefix("c.") {
+            Some(condition) => {
+                let condition = CONDITIONS
+                    .iter()
+                    .position(|name| *name == condition)
+                    .ok_or_else(unknown)?;
+                (0x30 | condition as u32, Shape::Compare)
+            }
+            None => return Err(unknown()),
+        },
+    };
+
+    // Only conversions from words take the word format.
+    if fmt == FMT_WORD && funct != 0x20 && funct != 0x21 {
+        return Err(unknown());
+    }
+
+    let (ft, fs, fd) = match shape {
+        Shape::Binary => {
+            i.expect(3)?;
+            (i.fpr(2)?, i.fpr(1)?, i.fpr(0)?)
+        }
+        Shape::Unary => {
+            i.expect(2)?;
+            (0, i.fpr(1)?, i.fpr(0)?)
+        }
+        // The low bit of the ft field selects movt, and the rest holds
+        // the condition code.
+        Shape::ConditionalOnCode => {
+            i.expect(3)?;
+            let tf = if operation == "movt" { 1 } else { 0 };
+            ((i.condition_code(2)? << 2) | tf, i.fpr(1)?, i.fpr(0)?)
+        }
+        Shape::ConditionalOnRegister => {
+            i.expect(3)?;
+            (i.gpr(2)?, i.fpr(1)?, i.fpr(0)?)
+        }
+        // The condition code defaults to 0, and is held in the upper
+        // three bits of the fd field.
+        Shape::Compare if i.operands.len() == 3 => {
+            (i.fpr(2)?, i.fpr(1)?, i.condition_code(0)? << 2)
+        }
+        Shape::Compare => {
+            i.expect(2)?;
+            (i.fpr(1)?, i.fpr(0)?, 0)
+        }
+    };
+
+    Ok((17 << 26) | (fmt << 21) | (ft << 16) | (fs << 11) | (fd << 6) | funct)
+}
+
+// The operands taken by a floating-point instruction.
+enum Shape {
+    /// fd, fs, ft
+    Binary,
+    /// fd, fs
+    Unary,
+    /// fd, fs, cc
+    ConditionalOnCode,
+    /// fd, fs, rt
+    ConditionalOnRegister,
+    /// [cc,] fs, ft
+    Compare,
+}
+
+fn r_type(rs: u32, rt: u32, rd: u32, shamt: u32, funct: u32) -> u32 {
+    (rs << 21) | (rt << 16) | (rd << 11) | (shamt << 6) | funct
+}
+
+fn i_type(opcode: u32, rs: u32, rt: u32, immediate: u32) -> u32 {
+    (opcode << 26) | (rs << 21) | (rt << 16) | immediate
+}
+
+fn cop_move(opcode: u32, rs: u32, rt: u32, rd: u32) -> u32 {
+    (opcode << 26) | (rs << 21) | (rt << 16) | (rd << 11)
+}
+
+pub fn check_range(value: i64, min: i64, max: i64, column: usize) -> Result<i64> {
+    if value < min || value > max {
+        return Err((
+            column,
+            AssemblyErrorKind::ImmediateOutOfRange { value, min, max },
+        ));
+    }
+
+    Ok(value)
+}
+
+/// The number of a general-purpose register, by name or number.
+pub fn gpr_number(name: &str) -> Option<u32> {
+    match name.parse::<u32>() {
+        Ok(number) if number < 32 => Some(number),
+        Ok(_) => None,
+        Err(_) => GPR_NAMES
+            .iter()
+            .position(|gpr| *gpr == name)
+            .map(|number| number as u32),
+    }
+}
+
+/// The number of a floating-point register, such as `f12`.
+pub fn fpr_number(name: &str) -> Option<u32> {
+    match name.strip_prefix('f')?.parse::<u32>() {
+        Ok(number) if number < 32 => Some(number),
+        _ => None,
+    }
+}
@@ -0,0 +1,188 @@
+use super::AssemblyErrorKind;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Mnemonics, labels and directives, such as `add.s`, `loop` or `.text`.
+    Identifier(String),
+    /// A register, without the leading `$`.
+    Register(String),
+    /// A macro parameter, without the leading `%`.
+    Parameter(String),
+    Integer(i64),
+    Float(f64),
+    /// A string literal, with escapes already processed.
+    String(String),
+    Comma,
+    LeftParen,
+    RightParen,
+    Colon,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Where the token starts in its line, counting from 1.
+    pub column: usize,
+    /// The number of characters the token spans.
+    pub length: usize,
+}
+
+/// Split one line of assembly into tokens, dropping any comment.
+pub fn tokenize(line: &str) -> Result<Vec<Token>, (usize, AssemblyErrorKind)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+
+        let kind = match c {
+            '#' => break,
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ',' => {
+                i += 1;
+                TokenKind::Comma
+            }
+            '(' => {
+                i += 1;
+                TokenKind::LeftParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RightParen
+            }
+            ':' => {
+                i += 1;
+                TokenKind::Colon
+            }
+            '$' => {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                TokenKind::Register(chars[start + 1..i].iter().collect())
+            }
+            '%' => {
+                i += 1;
+                while i < chars.len() && is_identifier_continue(chars[i]) {
+                    i += 1;
+                }
+                TokenKind::Parameter(chars[start + 1..i].iter().collect())
+            }
+            '"' => {
+                let (string, end) = lex_string(&chars, start)?;
+                i = end;
+                TokenKind::String(string)
+            }
+            // A character literal stands for its code.
+            '\'' => {
+                let (string, end) = lex_string(&chars, start)?;
+                let mut characters = string.chars();
+                let (Some(c), None) = (characters.next(), characters.next()) else {
+                    return Err((start + 1, AssemblyErrorKind::InvalidNumber(string)));
+                };
+                i = end;
+                TokenKind::Integer(c as i64)
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' => {
+                i += 1;
+                while i < chars.len() && is_number_continue(chars[i], chars[i - 1]) {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                match parse_integer(&text) {
+                    Some(value) => TokenKind::Integer(value),
+                    None => match text.parse::<f64>() {
+                        Ok(value) => TokenKind::Float(value),
+                        Err(_) => return Err((start + 1, AssemblyErrorKind::InvalidNumber(text))),
+                    },
+                }
+            }
+            c if is_identifier_start(c) => {
+                i += 1;
+                while i < chars.len() && is_identifier_continue(chars[i]) {
+                    i += 1;
+                }
+                TokenKind::Identifier(chars[start..i].iter().collect())
+            }
+            c => return Err((start + 1, AssemblyErrorKind::UnexpectedCharacter(c))),
+        };
+
+        tokens.push(Token {
+            kind,
+            column: start + 1,
+            length: i - start,
+        });
+    }
+
+    Ok(tokens)
+}
+
+// Numbers may be hexadecimal or have a fraction and a signed exponent.
+fn is_number_continue(c: char, previous: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || c == '_'
+        || c == '.'
+        || ((c == '-' || c == '+') && (previous == 'e' || previous == 'E'))
+}
+
+/// Read a string or character literal starting at its opening quote,
+/// returning its contents and the index after the closing quote.
+fn lex_string(chars: &[char], start: usize) -> Result<(String, usize), (usize, AssemblyErrorKind)> {
+    let quote = chars[start];
+    let mut string = String::new();
+    let mut i = start + 1;
+
+    loop {
+        let c = *chars
+            .get(i)
+            .ok_or((start + 1, AssemblyErrorKind::UnterminatedString))?;
+        i += 1;
+
+        match c {
+            c if c == quote => return Ok((string, i)),
+            '\\' => {
+                let escape = *chars
+                    .get(i)
+                    .ok_or((start + 1, AssemblyErrorKind::UnterminatedString))?;
+                i += 1;
+                string.push(match escape {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    c => c,
+                });
+            }
+            c => string.push(c),
+        }
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '.'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Parse a decimal or `0x` hexadecimal integer, with an optional sign.
+fn parse_integer(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    let magnitude = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+
+    Some(if negative { -magnitude } else { magnitude })
+}
@@ -0,0 +1,462 @@
+// Expand `.include` files, `.eqv` constants and macros into the lines the
+// assembler sees. Expansion works on tokens, so every line keeps the file,
+// line and columns it was written at, and errors point there rather than
+// into the expanded text.
+
+use super::lexer::{self, Token, TokenKind};
+use super::{AssemblyError, AssemblyErrorKind, Diagnostics, SourceLine};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How deeply macros and included files may nest.
+pub const MAX_DEPTH: usize = 16;
+
+/// A line ready to be assembled.
+pub struct Line {
+    pub tokens: Vec<Token>,
+    /// Where the tokens were written.
+    pub location: SourceLine,
+    /// The line outside any macro that produced the tokens.
+    pub origin: SourceLine,
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<(Vec<Token>, SourceLine)>,
+    // Labels defined in the body, which are renamed in each expansion so
+    // that a macro can be used more than once.
+    labels: Vec<String>,
+}
+
+// A macro whose body is still being read.
+struct Definition {
+    name: String,
+    macro_: Macro,
+    location: SourceLine,
+    column: usize,
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    lines: Vec<Line>,
+    equivalences: HashMap<String, Vec<Token>>,
+    // Macros may be overloaded on their number of parameters.
+    macros: HashMap<(String, usize), Macro>,
+    definition: Option<Definition>,
+    expansions: usize,
+    // Set once the recursion limit is reported, so that the lines expanded
+    // around it are dropped rather than each reaching the limit again.
+    unwinding: bool,
+    diagnostics: Diagnostics,
+}
+
+type Result<T> = std::result::Result<T, AssemblyError>;
+
+/// Expand a source file, whose includes are found relative to its own
+/// directory, or to the current directory when it has no path. Lines with
+/// errors are reported and left out.
+pub fn preprocess(source: &str, file: Option<&Path>) -> (Vec<Line>, Diagnostics) {
+    let mut preprocessor = Preprocessor::default();
+    preprocessor.source(source, file, 0);
+    (preprocessor.lines, preprocessor.diagnostics)
+}
+
+impl Preprocessor {
+    fn source(&mut self, source: &str, file: Option<&Path>, depth: usize) {
+        self.diagnostics.add_source(file, source);
+
+        for (index, text) in source.lines().enumerate() {
+            let location = SourceLine {
+                file: file.map(Path::to_path_buf),
+                line: index + 1,
+            };
+            match lexer::tokenize(text) {
+                Ok(tokens) => {
+                    let result = self.line(tokens, &location, &location, depth);
+                    self.check(result);
+                }
+                Err((column, kind)) => self
+                    .diagnostics
+                    .report(AssemblyError::new(kind, &location, column)),
+            }
+
+            // An included file stops at the recursion limit; the file that
+            // included it first carries on with its next line.
+            if self.unwinding {
+                if depth > 0 {
+                    break;
+                }
+                self.unwinding = false;
+            }
+        }
+
+        // A macro may not continue past the end of its file.
+        if let Some(definition) = self.definition.take() {
+            self.diagnostics.report(AssemblyError::new(
+                AssemblyErrorKind::UnterminatedMacro(definition.name),
+                &definition.location,
+                definition.column,
+            ));
+        }
+    }
+
+    // Report an error from a line. The recursion limit is reported where it
+    // is reached, and nowhere further out.
+    fn check(&mut self, result: Result<()>) {
+        if let Err(error) = result {
+            self.unwinding = error.kind == AssemblyErrorKind::RecursionLimit;
+            self.diagnostics.report(error);
+        }
+    }
+
+    fn line(
+        &mut self,
+        tokens: Vec<Token>,
+        location: &SourceLine,
+        origin: &SourceLine,
+        depth: usize,
+    ) -> Result<()> {
+        let error = |column, kind| AssemblyError::new(kind, location, column);
+        let labels = label_count(&tokens);
+        let head = tokens[labels..]
+            .first()
+            .map(|token| (&token.kind, token.column));
+
+        if let Some(definition) = &mut self.definition {
+            return match head {
+                Some((TokenKind::Identifier(name), _)) if name == ".end_macro" => {
+                    let definition = self.definition.take().unwrap();
+                    let count = definition.macro_.parameters.len();
+                    self.macros
+                        .insert((definition.name, count), definition.macro_);
+                    Ok(())
+                }
+                Some((TokenKind::Identifier(name), column)) if name == ".macro" => {
+                    Err(error(column, AssemblyErrorKind::NestedMacro))
+                }
+                _ => {
+                    for pair in tokens[..labels].chunks(2) {
+                        if let TokenKind::Identifier(label) = &pair[0].kind {
+                            definition.macro_.labels.push(label.clone());
+                        }
+                    }
+                    definition.macro_.body.push((tokens, location.clone()));
+                    Ok(())
+                }
+            };
+        }
+
+        let Some((TokenKind::Identifier(name), column)) = head else {
+            self.push(self.substitute(tokens), location, origin);
+            return Ok(());
+        };
+        let name = name.clone();
+        let arguments = &tokens[labels + 1..];
+
+        match name.as_str() {
+            ".macro" => {
+                self.push_labels(&tokens[..labels], location, origin);
+                self.define(arguments, location, column)
+            }
+            ".end_macro" => Err(error(column, AssemblyErrorKind::UnexpectedEndMacro)),
+            ".eqv" => {
+                let [Token {
+                    kind: TokenKind::Identifier(constant),
+                    ..
+                }, value @ ..] = arguments
+                else {
+                    return Err(error(
+                        column,
+                        AssemblyErrorKind::InvalidOperand {
+                            expected: "a name and a value",
+                        },
+                    ));
+                };
+                if value.is_empty() {
+                    return Err(error(
+                        column,
+                        AssemblyErrorKind::InvalidOperand {
+                            expected: "a name and a value",
+                        },
+                    ));
+                }
+                let value = self.substitute(value.to_vec());
+                self.equivalences.insert(constant.clone(), value);
+                self.push_labels(&tokens[..labels], location, origin);
+                Ok(())
+            }
+            ".include" => {
+                let [Token {
+                    kind: TokenKind::String(path),
+                    column: path_column,
+                    ..
+                }] = arguments
+                else {
+                    return Err(error(
+                        column,
+                        AssemblyErrorKind::InvalidOperand {
+                            expected: "a file name",
+                        },
+                    ));
+                };
+                if depth >= MAX_DEPTH {
+                    return Err(error(column, AssemblyErrorKind::RecursionLimit));
+                }
+
+                let path = match &location.file {
+                    Some(file) => file.parent().unwrap_or(Path::new("")).join(path),
+                    None => PathBuf::from(path),
+                };
+                let source = std::fs::read_to_string(&path).map_err(|io_error| {
+                    error(
+                        *path_column,
+                        AssemblyErrorKind::UnreadableFile {
+                            path: path.display().to_string(),
+                            message: io_error.to_string(),
+                        },
+                    )
+                })?;
+
+                self.push_labels(&tokens[..labels], location, origin);
+                self.source(&source, Some(&path), depth + 1);
+                Ok(())
+            }
+            _ => {
+                // `push ($sp)` passes one memory operand, while `push($sp)`
+                // and `sum (a, b)` pass lists in parentheses.
+                let name_end = column + name.len();
+                let arguments = match arguments {
+                    [Token {
+                        kind: TokenKind::LeftParen,
+                        column: paren,
+                        ..
+                    }, ..]
+                        if *paren > name_end
+                            && !arguments.iter().any(|t| t.kind == TokenKind::Comma) =>
+                    {
+                        vec![arguments.to_vec()]
+                    }
+                    _ => split_arguments(arguments),
+                };
+                if let Some(macro_) = self.macros.get(&(name.clone(), arguments.len())) {
+                    if depth >= MAX_DEPTH {
+                        return Err(error(column, AssemblyErrorKind::RecursionLimit));
+                    }
+
+                    let lines = self.expand(macro_, &arguments);
+                    self.expansions += 1;
+                    self.push_labels(&tokens[..labels], location, origin);
+                    for (tokens, body_location) in lines {
+                        let result = self.line(tokens, &body_location, origin, depth + 1);
+                        self.check(result);
+                        if self.unwinding {
+                            break;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                if let Some(((_, expected), _)) = self
+                    .macros
+                    .iter()
+                    .find(|((macro_name, _), _)| *macro_name == name)
+                {
+                    return Err(error(
+                        column,
+                        AssemblyErrorKind::WrongOperandCount {
+                            expected: *expected,
+                            found: arguments.len(),
+                        },
+                    ));
+                }
+
+                self.push(self.substitute(tokens), location, origin);
+                Ok(())
+            }
+        }
+    }
+
+    // Start a definition from the tokens after `.macro`: a name, then
+    // parameters, which may be in parentheses.
+    fn define(&mut self, tokens: &[Token], location: &SourceLine, column: usize) -> Result<()> {
+        let invalid = |column| {
+            AssemblyError::new(
+                AssemblyErrorKind::InvalidOperand {
+                    expected: "a macro name and parameters",
+                },
+                location,
+                column,
+            )
+        };
+
+        let Some((
+            Token {
+                kind: TokenKind::Identifier(name),
+                ..
+            },
+            rest,
+        )) = tokens.split_first()
+        else {
+            return Err(invalid(column));
+        };
+
+        let mut parameters = Vec::new();
+        for argument in split_arguments(rest) {
+            match argument.as_slice() {
+                [Token {
+                    kind: TokenKind::Parameter(parameter),
+                    ..
+                }] => parameters.push(parameter.clone()),
+                tokens => return Err(invalid(tokens.first().map_or(column, |t| t.column))),
+            }
+        }
+
+        self.definition = Some(Definition {
+            name: name.clone(),
+            macro_: Macro {
+                parameters,
+                body: Vec::new(),
+                labels: Vec::new(),
+            },
+            location: location.clone(),
+            column,
+        });
+        Ok(())
+    }
+
+    // The body of a macro with its parameters replaced by the arguments,
+    // which take the columns of the parameters they replace.
+    fn expand(&self, macro_: &Macro, arguments: &[Vec<Token>]) -> Vec<(Vec<Token>, SourceLine)> {
+        let suffix = format!("_M{}", self.expansions);
+
+        macro_
+            .body
+            .iter()
+            .map(|(tokens, location)| {
+                let mut expanded = Vec::new();
+                for token in tokens {
+                    match &token.kind {
+                        TokenKind::Parameter(parameter) => {
+                            match macro_.parameters.iter().position(|p| p == parameter) {
+                                Some(index) => {
+                                    expanded.extend(arguments[index].iter().map(|argument| Token {
+                                        kind: argument.kind.clone(),
+                                        column: token.column,
+                                        length: token.length,
+                                    }))
+                                }
+                                None => expanded.push(token.clone()),
+                            }
+                        }
+                        TokenKind::Identifier(name) if macro_.labels.contains(name) => expanded
+                            .push(Token {
+                                kind: TokenKind::Identifier(format!("{name}{suffix}")),
+                                column: token.column,
+                                length: token.length,
+                            }),
+                        _ => expanded.push(token.clone()),
+                    }
+                }
+                (expanded, location.clone())
+            })
+            .collect()
+    }
+
+    // Replace `.eqv` constants with their values, leaving the names of
+    // labels being defined alone.
+    fn substitute(&self, tokens: Vec<Token>) -> Vec<Token> {
+        if self.equivalences.is_empty() {
+            return tokens;
+        }
+
+        let labels = label_count(&tokens);
+        let mut substituted = Vec::with_capacity(tokens.len());
+        let mut tokens = tokens.into_iter();
+        substituted.extend(tokens.by_ref().take(labels));
+        for token in tokens {
+            match &token.kind {
+                TokenKind::Identifier(name) if self.equivalences.contains_key(name) => substituted
+                    .extend(self.equivalences[name].iter().map(|value| Token {
+                        kind: value.kind.clone(),
+                        column: token.column,
+                        length: token.length,
+                    })),
+                _ => substituted.push(token),
+            }
+        }
+        substituted
+    }
+
+    fn push(&mut self, tokens: Vec<Token>, location: &SourceLine, origin: &SourceLine) {
+        self.lines.push(Line {
+            tokens,
+            location: location.clone(),
+            origin: origin.clone(),
+        });
+    }
+
+    // Labels before a directive or macro call are kept on a line of their
+    // own.
+    fn push_labels(&mut self, labels: &[Token], location: &SourceLine, origin: &SourceLine) {
+        if !labels.is_empty() {
+            self.push(labels.to_vec(), location, origin);
+        }
+    }
+}
+
+// The number of tokens taken by `label:` definitions at the start of a
+// line.
+fn label_count(tokens: &[Token]) -> usize {
+    let mut count = 0;
+    while let [Token {
+        kind: TokenKind::Identifier(_),
+        ..
+    }, Token {
+        kind: TokenKind::Colon,
+        ..
+    }, ..] = &tokens[count..]
+    {
+        count += 2;
+    }
+    count
+}
+
+// Split macro arguments or parameters on commas, dropping any parentheses
+// around the whole list.
+fn split_arguments(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let tokens = match tokens {
+        [Token {
+            kind: TokenKind::LeftParen,
+            ..
+        }, inner @ .., Token {
+            kind: TokenKind::RightParen,
+            ..
+        }] if balanced(inner) => inner,
+        _ => tokens,
+    };
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    tokens
+        .split(|token| token.kind == TokenKind::Comma)
+        .map(<[Token]>::to_vec)
+        .collect()
+}
+
+// Whether no parenthesis closes before it opens, so that parentheses around
+// the tokens match each other rather than ones inside, as in `($t0), ($t1)`.
+fn balanced(tokens: &[Token]) -> bool {
+    let mut depth = 0usize;
+    for token in tokens {
+        match token.kind {
+            TokenKind::LeftParen => depth += 1,
+            TokenKind::RightParen => match depth.checked_sub(1) {
+                Some(outer) => depth = outer,
+                None => return false,
+            },
+            _ => {}
+        }
+    }
+    depth == 0
+}
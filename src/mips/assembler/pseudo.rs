@@ -0,0 +1,225 @@
+// Pseudo-instructions, which expand into sequences of real instructions.
+//
+// Sequences that need a scratch register use $at, so it may not appear in
+// their operands. The size of an expansion never depends on the value of a
+// label, so addresses can be assigned before every label is known.
+
+use super::encoder::{check_range, gpr_number, Operand, OperandKind};
+use super::AssemblyErrorKind;
+
+type Result<T> = std::result::Result<T, (usize, AssemblyErrorKind)>;
+
+/// A real instruction produced by an expansion.
+pub type Expanded = (String, Vec<Operand>);
+
+/// Expand a pseudo-instruction, or return `None` for a real instruction.
+pub fn expand(
+    mnemonic: &str,
+    column: usize,
+    operands: &[Operand],
+) -> Result<Option<Vec<Expanded>>> {
+    let expect = |count: usize| {
+        if operands.len() != count {
+            return Err((
+                column,
+                AssemblyErrorKind::WrongOperandCount {
+                    expected: count,
+                    found: operands.len(),
+                },
+            ));
+        }
+        Ok(())
+    };
+    let zero = register("zero", column);
+    let at = register("at", column);
+    let op = |index: usize| operands[index].clone();
+
+    let expansion = match mnemonic {
+        "move" => {
+            expect(2)?;
+            vec![instruction("addu", [op(0), zero, op(1)])]
+        }
+        "not" => {
+            expect(2)?;
+            vec![instruction("nor", [op(0), op(1), zero])]
+        }
+        "neg" => {
+            expect(2)?;
+            vec![instruction("sub", [op(0), zero, op(1)])]
+        }
+        // The product is also left in LO, and its upper half in HI.
+        "mul" => {
+            expect(3)?;
+            vec![
+                instruction("mult", [op(1), op(2)]),
+                instruction("mflo", [op(0)]),
+            ]
+        }
+        "b" => {
+            expect(1)?;
+            vec![instruction("beq", [zero.clone(), zero, op(0)])]
+        }
+        "beqz" | "bnez" => {
+            expect(2)?;
+            let branch = if mnemonic == "beqz" { "beq" } else { "bne" };
+            vec![instruction(branch, [op(0), zero, op(1)])]
+        }
+        // Compare into $at, then branch on whether it was set. An immediate
+        // is loaded into $at first.
+        "blt" | "bgt" | "ble" | "bge" => {
+            expect(3)?;
+            reserve_at(operands)?;
+            let mut expansion = Vec::new();
+            let second = match operands[1].kind {
+                OperandKind::Immediate(_) => {
+                    expansion = load_immediate(at.clone(), integer(&operands[1])?, column);
+                    at.clone()
+                }
+                _ => op(1),
+            };
+            let (left, right) = match mnemonic {
+                "blt" | "bge" => (op(0), second),
+                _ => (second, op(0)),
+            };
+            let branch = match mnemonic {
+                "blt" | "bgt" => "bne",
+                _ => "beq",
+            };
+            expansion.push(instruction("slt", [at.clone(), left, right]));
+            expansion.push(instruction(branch, [at, zero, op(2)]));
+            expansion
+        }
+        "li" => {
+            expect(2)?;
+            let value = integer(&operands[1])?;
+            load_immediate(op(0), value, operands[1].column)
+        }
+        "la" => {
+            expect(2)?;
+            match &operands[1].kind {
+                OperandKind::Label(name) => {
+                    let label_column = operands[1].column;
+                    vec![
+                        instruction(
+                            "lui",
+                            [
+                                op(0),
+                                operand(OperandKind::UpperHalf(name.clone()), label_column),
+                            ],
+                        ),
+                        instruction(
+                            "ori",
+                            [
+                                op(0),
+                                op(0),
+                                operand(OperandKind::LowerHalf(name.clone()), label_column),
+                            ],
+                        ),
+                    ]
+                }
+                _ => {
+                    let value = integer(&operands[1])?;
+                    load_immediate(op(0), value, operands[1].column)
+                }
+            }
+        }
+        // An immediate too large for the instruction is loaded into $at,
+        // and the register form used instead.
+        "addi" | "addiu" | "slti" | "sltiu" | "andi" | "ori" | "xori" if operands.len() == 3 => {
+            let OperandKind::Immediate(value) = operands[2].kind else {
+                return Ok(None);
+            };
+            let (range, register_form) = match mnemonic {
+                "addi" => (-0x8000..=0x7FFF, "add"),
+                "addiu" => (-0x8000..=0x7FFF, "addu"),
+                "slti" => (-0x8000..=0x7FFF, "slt"),
+                "sltiu" => (-0x8000..=0x7FFF, "sltu"),
+                "andi" => (0..=0xFFFF, "and"),
+                "ori" => (0..=0xFFFF, "or"),
+                _ => (0..=0xFFFF, "xor"),
+            };
+            if range.contains(&value) {
+                return Ok(None);
+            }
+
+            reserve_at(operands)?;
+            let mut expansion = load_immediate(at.clone(), integer(&operands[2])?, column);
+            expansion.push(instruction(register_form, [op(0), op(1), at]));
+            expansion
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(expansion))
+}
+
+// Load a 32-bit value in one instruction where possible, or two.
+fn load_immediate(destination: Operand, value: i64, column: usize) -> Vec<Expanded> {
+    let immediate = |value| operand(OperandKind::Immediate(value), column);
+
+    if (-0x8000..=0x7FFF).contains(&value) {
+        return vec![instruction(
+            "addiu",
+            [destination, register("zero", column), immediate(value)],
+        )];
+    }
+    if (0..=0xFFFF).contains(&value) {
+        return vec![instruction(
+            "ori",
+            [destination, register("zero", column), immediate(value)],
+        )];
+    }
+
+    let upper = (value >> 16) & 0xFFFF;
+    let lower = value & 0xFFFF;
+    let mut expansion = vec![instruction("lui", [destination.clone(), immediate(upper)])];
+    if lower != 0 {
+        expansion.push(instruction(
+            "ori",
+            [destination.clone(), destination, immediate(lower)],
+        ));
+    }
+    expansion
+}
+
+// A 32-bit value, either signed or unsigned.
+fn integer(operand: &Operand) -> Result<i64> {
+    match operand.kind {
+        OperandKind::Immediate(value) => {
+            check_range(value, i32::MIN as i64, u32::MAX as i64, operand.column)
+        }
+        _ => Err((
+            operand.column,
+            AssemblyErrorKind::InvalidOperand {
+                expected: "an immediate",
+            },
+        )),
+    }
+}
+
+fn reserve_at(operands: &[Operand]) -> Result<()> {
+    for operand in operands {
+        let register = match &operand.kind {
+            OperandKind::Register(name) => name,
+            OperandKind::Memory { base, .. } => base,
+            _ => continue,
+        };
+        if gpr_number(register) == Some(1) {
+            return Err((operand.column, AssemblyErrorKind::ReservedRegister));
+        }
+    }
+
+    Ok(())
+}
+
+fn instruction<const N: usize>(mnemonic: &str, operands: [Operand; N]) -> Expanded {
+    (mnemonic.to_string(), operands.into())
+}
+
+fn operand(kind: OperandKind, column: usize) -> Operand {
+    Operand { kind, column }
+}
+
+fn register(name: &str, column: usize) -> Operand {
+    operand(OperandKind::Register(name.to_string()), column)
+}
@@ -0,0 +1,142 @@
+use super::{device::Device, error::ErrorKind};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Where SPIM and MARS map the keyboard and display registers.
+pub const CONSOLE_BASE: u64 = 0xFFFF_0000;
+pub const CONSOLE_END: u64 = 0xFFFF_0010;
+
+// Register offsets from the console base.
+const RECEIVER_CONTROL: u64 = 0x0;
+const RECEIVER_DATA: u64 = 0x4;
+const TRANSMITTER_CONTROL: u64 = 0x8;
+const TRANSMITTER_DATA: u64 = 0xC;
+
+/// Control register bits: the device is ready, and it may interrupt.
+pub const CONTROL_READY: u64 = 1 << 0;
+pub const CONTROL_INTERRUPT_ENABLE: u64 = 1 << 1;
+
+/// The interrupt lines raised by the keyboard and display, as in MARS.
+/// These are Cause bits 8 and 9.
+pub const KEYBOARD_INTERRUPT: u8 = 1 << 0;
+pub const DISPLAY_INTERRUPT: u8 = 1 << 1;
+
+/// The SPIM/MARS memory-mapped keyboard and display.
+///
+/// Clones share the same state, so one can be attached to the bus while
+/// the host keeps another to type input and collect output.
+#[derive(Clone, Default)]
+pub struct Console(Rc<RefCell<ConsoleState>>);
+
+#[derive(Default)]
+struct ConsoleState {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+    receiver_interrupt_enable: bool,
+    transmitter_interrupt_enable: bool,
+    // The display became ready again after a character while its interrupt
+    // was enabled, and the program has not yet looked at it.
+    transmitter_interrupt: bool,
+}
+
+impl Console {
+    /// Queue bytes for the program to read from the keyboard.
+    pub fn push_input(&self, bytes: &[u8]) {
+        self.0.borrow_mut().input.extend(bytes);
+    }
+
+    /// Everything the program has written to the display so far, as text.
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow().output).into_owned()
+    }
+
+    /// Remove and return everything written to the display so far.
+    pub fn take_output(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.borrow_mut().output)
+    }
+}
+
+// The display accepts characters as fast as they are written, so the
+// transmitter is always ready. As in MARS, it interrupts when it becomes
+// ready again after each character, rather than for as long as it is ready;
+// the interrupt is cleared when the program next reads or writes a
+// transmitter register.
+//
+// The registers are only accessed whole, with lw and sw. Byte, halfword
+// and doubleword accesses are bus errors.
+impl Device for Console {
+    fn read(&mut self, offset: u64, size: u64) -> Result<u64, ErrorKind> {
+        if size != 4 {
+            return Err(ErrorKind::BusError { address: offset });
+        }
+        let mut state = self.0.borrow_mut();
+
+        Ok(match offset {
+            RECEIVER_CONTROL => {
+                let ready = !state.input.is_empty();
+                control(ready, state.receiver_interrupt_enable)
+            }
+            // Reading the data register consumes the character.
+            RECEIVER_DATA => state.input.pop_front().unwrap_or(0) as u64,
+            TRANSMITTER_CONTROL => {
+                state.transmitter_interrupt = false;
+                control(true, state.transmitter_interrupt_enable)
+            }
+            TRANSMITTER_DATA => 0,
+            _ => return Err(ErrorKind::BusError { address: offset }),
+        })
+    }
+
+    fn write(&mut self, offset: u64, size: u64, value: u64) -> Result<(), ErrorKind> {
+        if size != 4 {
+            return Err(ErrorKind::BusError { address: offset });
+        }
+        let mut state = self.0.borrow_mut();
+
+        // Only the interrupt enable bits and the transmitter data are
+        // writable.
+        match offset {
+            RECEIVER_CONTROL => {
+                state.receiver_interrupt_enable = value & CONTROL_INTERRUPT_ENABLE != 0
+            }
+            TRANSMITTER_CONTROL => {
+                state.transmitter_interrupt_enable = value & CONTROL_INTERRUPT_ENABLE != 0;
+                state.transmitter_interrupt = false;
+            }
+            TRANSMITTER_DATA => {
+                state.output.push(value as u8);
+                state.transmitter_interrupt = state.transmitter_interrupt_enable;
+            }
+            RECEIVER_DATA => (),
+            _ => return Err(ErrorKind::BusError { address: offset }),
+        }
+
+        Ok(())
+    }
+
+    fn interrupts(&self) -> u8 {
+        let state = self.0.borrow();
+        let mut interrupts = 0;
+
+        if state.receiver_interrupt_enable && !state.input.is_empty() {
+            interrupts |= KEYBOARD_INTERRUPT;
+        }
+        if state.transmitter_interrupt {
+            interrupts |= DISPLAY_INTERRUPT;
+        }
+
+        interrupts
+    }
+}
+
+fn control(ready: bool, interrupt_enable: bool) -> u64 {
+    let mut value = 0;
+    if ready {
+        value |= CONTROL_READY;
+    }
+    if interrupt_enable {
+        value |= CONTROL_INTERRUPT_ENABLE;
+    }
+    value
+}
@@ -3,7 +3,14 @@ pub struct ControlSignals {
     pub alu_control: AluControl,
     pub alu_op: AluOp,
     pub alu_src: AluSrc,
+    pub alu_src_1: AluSrc1,
     pub branch: Branch,
+    pub branch_type: BranchType,
+    pub cp0_write: Cp0Write,
+    pub fpu_cc_write: FpuCcWrite,
+    pub fpu_reg_dst: FpuRegDst,
+    pub fpu_reg_write: FpuRegWrite,
+    pub hi_lo_write: HiLoWrite,
     pub jump: Jump,
     pub mem_read: MemRead,
     pub mem_to_reg: MemToReg,
@@ -24,6 +31,13 @@ pub enum AluControl {
     Or = 5,
     LeftShift16 = 6,
     Not = 7,
+    Xor = 8,
+    ShiftLeftLogical = 9,
+    ShiftRightLogical = 10,
+    ShiftRightArithmetic = 11,
+    AdditionUnsigned = 12,
+    SubtractionUnsigned = 13,
+    Nor = 14,
 }
 
 #[derive(Default)]
@@ -37,6 +51,9 @@ pub enum AluOp {
     Or = 5,
     LeftShift16 = 6,
     UseFunctField = 7,
+    Xor = 8,
+    AdditionUnsigned = 9,
+    SubtractionUnsigned = 10,
 }
 
 #[derive(Default)]
@@ -44,9 +61,17 @@ pub enum AluSrc {
     #[default]
     ReadRegister2 = 0,
     ExtendedImmediate = 1,
+    ZeroExtendedImmediate = 2,
 }
 
 #[derive(Default)]
+pub enum AluSrc1 {
+    #[default]
+    ReadRegister1 = 0,
+    Shamt = 1,
+}
+
+#[derive(Default, Eq, PartialEq)]
 pub enum Branch {
     #[default]
     NoBranch = 0,
@@ -54,13 +79,67 @@ pub enum Branch {
 }
 
 #[derive(Default)]
+pub enum BranchType {
+    #[default]
+    OnEqual = 0,
+    OnNotEqual = 1,
+    OnLessThanZero = 2,
+    OnLessThanOrEqualToZero = 3,
+    OnGreaterThanZero = 4,
+    OnGreaterThanOrEqualToZero = 5,
+    OnFpuConditionTrue = 6,
+    OnFpuConditionFalse = 7,
+}
+
+#[derive(Default, Eq, PartialEq)]
+pub enum Cp0Write {
+    #[default]
+    NoWrite = 0,
+    YesWrite = 1,
+}
+
+#[derive(Default, Eq, PartialEq)]
+pub enum FpuCcWrite {
+    #[default]
+    NoWrite = 0,
+    YesWrite = 1,
+}
+
+#[derive(Default)]
+pub enum FpuRegDst {
+    Ft = 0,
+    Fs = 1,
+    #[default]
+    Fd = 2,
+    ControlRegister = 3,
+}
+
+#[derive(Default, Eq, PartialEq)]
+pub enum FpuRegWrite {
+    #[default]
+    NoWrite = 0,
+    YesWrite = 1,
+}
+
+#[derive(Default, Eq, PartialEq)]
+pub enum HiLoWrite {
+    #[default]
+    NoWrite = 0,
+    WriteHi = 1,
+    WriteLo = 2,
+    WriteBoth = 3,
+}
+
+#[derive(Default, Eq, PartialEq)]
 pub enum Jump {
     #[default]
     NoJump = 0,
     YesJump = 1,
+    YesJumpRegister = 2,
+    YesExceptionReturn = 3,
 }
 
-#[derive(Default)]
+#[derive(Default, Eq, PartialEq)]
 pub enum MemRead {
     #[default]
     NoRead = 0,
@@ -72,16 +151,21 @@ pub enum MemToReg {
     #[default]
     UseAlu = 0,
     UseMemory = 1,
+    UsePcPlusFour = 2,
+    UseHi = 3,
+    UseLo = 4,
+    UseCoprocessor0 = 5,
+    UseFloatingPointUnit = 6,
 }
 
-#[derive(Default)]
+#[derive(Default, Eq, PartialEq)]
 pub enum MemWrite {
     #[default]
     NoWrite = 0,
     YesWrite = 1,
 }
 
-#[derive(Default)]
+#[derive(Default, Eq, PartialEq)]
 pub enum MemWriteSrc {
     #[default]
     PrimaryUnit = 0,
@@ -93,6 +177,7 @@ pub enum RegDst {
     Reg2 = 0,
     #[default]
     Reg3 = 1,
+    ReturnAddress = 2,
 }
 
 #[derive(Default, Eq, PartialEq)]
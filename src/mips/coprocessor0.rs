@@ -0,0 +1,62 @@
+/// The address of the general exception vector on the R2000/R3000 and in
+/// MIPS32 when the bootstrap vectors are not in use.
+pub const GENERAL_EXCEPTION_VECTOR: u64 = 0x8000_0180;
+
+/// Status register: interrupts are enabled.
+pub const STATUS_IE: u64 = 1 << 0;
+
+/// Status register: exception level. Set when an exception is taken and
+/// cleared by `eret`.
+pub const STATUS_EXL: u64 = 1 << 1;
+
+/// Cause register: location of the five-bit exception code.
+pub const CAUSE_EXC_CODE_SHIFT: u64 = 2;
+pub const CAUSE_EXC_CODE_MASK: u64 = 0b11111 << CAUSE_EXC_CODE_SHIFT;
+
+/// Cause register: the eight interrupt-pending bits. The interrupt mask
+/// bits of the Status register are in the same place.
+pub const CAUSE_IP_SHIFT: u64 = 8;
+pub const CAUSE_IP_MASK: u64 = 0xFF << CAUSE_IP_SHIFT;
+
+/// System control coprocessor (CP0) registers used for exception handling.
+#[derive(Debug, Default)]
+pub struct Coprocessor0 {
+    /// Register 8: the most recent address that caused an address error.
+    pub bad_vaddr: u64,
+    /// Register 12: processor status, including the exception level bit.
+    pub status: u64,
+    /// Register 13: the cause of the most recent exception.
+    pub cause: u64,
+    /// Register 14: the address to return to after handling an exception.
+    pub epc: u64,
+}
+
+impl Coprocessor0 {
+    /// Read a CP0 register by number, as `mfc0` does.
+    pub fn get(&self, register: u32) -> Option<u64> {
+        match register {
+            8 => Some(self.bad_vaddr),
+            12 => Some(self.status),
+            13 => Some(self.cause),
+            14 => Some(self.epc),
+            _ => None,
+        }
+    }
+
+    /// Write a CP0 register by number, as `mtc0` does.
+    ///
+    /// BadVAddr is read-only and, like unimplemented registers, ignores writes.
+    pub fn set(&mut self, register: u32, value: u64) {
+        match register {
+            12 => self.status = value,
+            13 => self.cause = value,
+            14 => self.epc = value,
+            _ => (),
+        }
+    }
+
+    /// Get the exception code field of the Cause register.
+    pub fn exception_code(&self) -> u32 {
+        ((self.cause & CAUSE_EXC_CODE_MASK) >> CAUSE_EXC_CODE_SHIFT) as u32
+    }
+}
@@ -1,13 +1,34 @@
-use super::{control_signals::*, memory::Memory, registers::Registers};
+use super::{
+    control_signals::*,
+    coprocessor0::{
+        Coprocessor0, CAUSE_EXC_CODE_MASK, CAUSE_EXC_CODE_SHIFT, CAUSE_IP_MASK, CAUSE_IP_SHIFT,
+        STATUS_EXL, STATUS_IE,
+    },
+    error::{ErrorKind, ExecutionError},
+    fpu::{self, RoundingMode},
+    memory::{Memory, MemoryConfig},
+    registers::Registers,
+    syscall::{SyscallHandler, SyscallMode},
+};
 use crate::datapath::Datapath;
 
 #[derive(Default)]
 pub struct MipsDatapath {
     pub registers: Registers,
+    pub coprocessor0: Coprocessor0,
     pub memory: Memory,
     pub instruction: u32,
     pub signals: ControlSignals,
 
+    /// Where to transfer control when an exception is raised.
+    ///
+    /// When unset, exceptions are instead returned to the caller as errors.
+    pub exception_vector: Option<u64>,
+
+    pub syscall_mode: SyscallMode,
+    pub syscalls: SyscallHandler,
+    pub state: ExecutionState,
+
     opcode: u32,
     rs: u32,
     rt: u32,
@@ -15,18 +36,43 @@ pub struct MipsDatapath {
     shamt: u32,
     funct: u32,
     imm: u32,
+    addr: u32,
 
     read_data_1: u64,
     read_data_2: u64,
+    fpu_read_data_1: u64,
+    fpu_read_data_2: u64,
     sign_extend: u64,
 
     alu_result: u64,
+    alu_zero: bool,
+    alu_overflow: bool,
+    hi_result: u64,
+    lo_result: u64,
+    fpu_result: u64,
     memory_data: u64,
     data_result: u64,
 
     current_stage: Stage,
 }
 
+/// Whether the program is still running.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ExecutionState {
+    #[default]
+    Running,
+    Exited(i32),
+}
+
+impl std::fmt::Display for ExecutionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionState::Running => write!(f, "program is running"),
+            ExecutionState::Exited(code) => write!(f, "program exited with code {code}"),
+        }
+    }
+}
+
 #[derive(Default, Copy, Clone, Eq, PartialEq)]
 enum Stage {
     #[default]
@@ -49,86 +95,172 @@ impl Stage {
     }
 }
 
-fn error(message: &str) {
-    panic!("{}", message);
-}
-
 impl Datapath for MipsDatapath {
-    fn execute_instruction(&mut self) {
-        println!("Running an instruction!");
+    type Error = ExecutionError;
 
+    fn execute_instruction(&mut self) -> Result<(), ExecutionError> {
         // If the last instruction has not finished, finish it instead.
         if self.current_stage != Stage::InstructionFetch {
-            self.finish_instruction();
-            return;
+            return self.finish_instruction();
         }
 
-        // IF
-        self.stage_instruction_fetch();
-
-        // ID
-        self.stage_instruction_decode();
-
-        // EX
-        self.stage_execute();
+        if self.is_halted() {
+            return Ok(());
+        }
 
-        // MEM
-        self.stage_memory();
+        let result = self
+            .stage_instruction_fetch() // IF
+            .and_then(|_| self.stage_instruction_decode()) // ID
+            .and_then(|_| self.stage_execute()) // EX
+            .and_then(|_| self.stage_memory()); // MEM
 
-        // WB
-        self.stage_writeback();
+        match result {
+            // WB
+            Ok(()) => {
+                self.stage_writeback();
+                Ok(())
+            }
+            Err(kind) => self.raise_exception(kind),
+        }
     }
 
-    fn execute_stage(&mut self) {
-        match self.current_stage {
+    fn execute_stage(&mut self) -> Result<(), ExecutionError> {
+        // Let the exiting instruction finish, but do not start another.
+        if self.is_halted() && self.current_stage == Stage::InstructionFetch {
+            return Ok(());
+        }
+
+        let result = match self.current_stage {
             Stage::InstructionFetch => self.stage_instruction_fetch(),
             Stage::InstructionDecode => self.stage_instruction_decode(),
             Stage::Execute => self.stage_execute(),
             Stage::Memory => self.stage_memory(),
-            Stage::WriteBack => self.stage_writeback(),
+            Stage::WriteBack => {
+                self.stage_writeback();
+                Ok(())
+            }
+        };
+        if let Err(kind) = result {
+            return self.raise_exception(kind);
         }
 
         self.current_stage = Stage::get_next_stage(self.current_stage);
+        Ok(())
     }
 
     fn get_register(&self, register: &str) -> Option<u64> {
         Some(self.registers[register])
     }
+
+    fn is_halted(&self) -> bool {
+        self.state != ExecutionState::Running
+    }
 }
 
 impl MipsDatapath {
-    fn finish_instruction(&mut self) {
+    /// A datapath with the given memory layout, with `pc`, `$gp` and `$sp`
+    /// set to their conventional starting values.
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            registers: Registers::new(&config),
+            memory: Memory::new(config),
+            ..Default::default()
+        }
+    }
+
+    fn finish_instruction(&mut self) -> Result<(), ExecutionError> {
         while self.current_stage != Stage::InstructionFetch {
-            self.execute_stage();
+            self.execute_stage()?;
         }
+
+        Ok(())
     }
 
-    fn stage_instruction_fetch(&mut self) {
-        self.instruction_fetch();
+    /// Abandon the current instruction because of an exception.
+    ///
+    /// Nothing from the instruction has been committed yet, so exceptions
+    /// are precise. If an exception vector is configured, the exception is
+    /// recorded in CP0 and control is transferred to the handler. Otherwise
+    /// it is returned as an error, and execution may resume by fetching
+    /// from the same PC.
+    fn raise_exception(&mut self, kind: ErrorKind) -> Result<(), ExecutionError> {
+        self.current_stage = Stage::InstructionFetch;
+
+        let Some(vector) = self.exception_vector else {
+            return Err(ExecutionError {
+                kind,
+                pc: self.registers.pc,
+                instruction: self.instruction,
+                segment: kind
+                    .address()
+                    .and_then(|address| self.memory.segment_at(address))
+                    .map(|segment| segment.kind),
+            });
+        };
+
+        // EPC is only updated if not already handling an exception.
+        if self.coprocessor0.status & STATUS_EXL == 0 {
+            self.coprocessor0.epc = self.registers.pc;
+        }
+        self.coprocessor0.status |= STATUS_EXL;
+        self.coprocessor0.cause = (self.coprocessor0.cause & !CAUSE_EXC_CODE_MASK)
+            | ((kind.exception_code() as u64) << CAUSE_EXC_CODE_SHIFT);
+        if let Some(address) = kind.bad_address() {
+            self.coprocessor0.bad_vaddr = address;
+        }
+
+        self.registers.pc = vector;
+        Ok(())
+    }
+
+    fn stage_instruction_fetch(&mut self) -> Result<(), ErrorKind> {
+        self.check_interrupts()?;
+        self.instruction_fetch()
     }
 
-    fn stage_instruction_decode(&mut self) {
+    fn stage_instruction_decode(&mut self) -> Result<(), ErrorKind> {
         self.instruction_decode();
         self.sign_extend();
-        self.set_control_signals();
+        self.set_control_signals()?;
         self.read_registers();
-        self.set_alu_control();
+        self.set_alu_control()
     }
 
-    fn stage_execute(&mut self) {
+    fn stage_execute(&mut self) -> Result<(), ErrorKind> {
         self.alu();
+        self.multiply_divide();
+        self.floating_point_unit()?;
+
+        // Emulated system calls are serviced here; in exception mode they
+        // never reach this stage.
+        if self.opcode == 0 && self.funct == 0b001100 {
+            if let Some(code) = self
+                .syscalls
+                .handle(&mut self.registers, &mut self.memory)?
+            {
+                self.state = ExecutionState::Exited(code);
+            }
+        }
+
+        // Signed overflow raises an Integer Overflow exception before
+        // the destination register is written.
+        if self.alu_overflow {
+            return Err(ErrorKind::IntegerOverflow);
+        }
+
+        Ok(())
     }
 
-    fn stage_memory(&mut self) {
-        /*
-        if self.signals.mem_read == 1{
-            self.memory_read();
+    fn stage_memory(&mut self) -> Result<(), ErrorKind> {
+        if self.signals.mem_read == MemRead::YesRead {
+            self.memory_read()?;
         }
 
-        if self.signals.mem_write == 1 {
-            self.memory_write();
+        if self.signals.mem_write == MemWrite::YesWrite {
+            self.memory_write()?;
         }
-        */
+
+        Ok(())
     }
 
     fn stage_writeback(&mut self) {
@@ -136,9 +268,43 @@ impl MipsDatapath {
         self.set_pc();
     }
 
-    fn instruction_fetch(&mut self) {
-        // Load instruction
-        self.instruction = self.memory.load_word(self.registers.pc);
+    /// Take a pending interrupt before fetching the next instruction.
+    ///
+    /// Devices drive the interrupt-pending bits of Cause. An interrupt is
+    /// taken if it is unmasked in Status, interrupts are enabled, no
+    /// exception is being handled, and there is a handler to take it.
+    fn check_interrupts(&mut self) -> Result<(), ErrorKind> {
+        let pending = self.memory.pending_interrupts() as u64;
+        self.coprocessor0.cause =
+            (self.coprocessor0.cause & !CAUSE_IP_MASK) | (pending << CAUSE_IP_SHIFT);
+
+        let status = self.coprocessor0.status;
+        if self.exception_vector.is_some()
+            && status & STATUS_IE != 0
+            && status & STATUS_EXL == 0
+            && self.coprocessor0.cause & status & CAUSE_IP_MASK != 0
+        {
+            return Err(ErrorKind::Interrupt);
+        }
+
+        Ok(())
+    }
+
+    fn instruction_fetch(&mut self) -> Result<(), ErrorKind> {
+        // Clear the previous instruction so a failed fetch is not
+        // reported against it.
+        self.instruction = 0;
+
+        // Load instruction. A misaligned PC is an address error, reported
+        // by memory like any other misaligned load.
+        self.instruction = self
+            .memory
+            .load_word(self.registers.pc)
+            .map_err(|kind| match kind {
+                ErrorKind::BusError { address } => ErrorKind::InstructionBusError { address },
+                kind => kind,
+            })?;
+        Ok(())
     }
 
     fn instruction_decode(&mut self) {
@@ -149,6 +315,7 @@ impl MipsDatapath {
         self.shamt = (self.instruction >> 6) & 0b11111;
         self.funct = self.instruction & 0b111111;
         self.imm = self.instruction & 0xFFFF;
+        self.addr = self.instruction & 0x03FF_FFFF;
     }
 
     fn sign_extend(&mut self) {
@@ -164,23 +331,365 @@ impl MipsDatapath {
         }
     }
 
-    fn set_control_signals(&mut self) {
+    fn set_control_signals(&mut self) -> Result<(), ErrorKind> {
         match self.opcode {
-            // R-type instructions (add, sub, and, or, slt, sltu)
+            // R-type instructions (add, sub, and, or, xor, slt, sltu, shifts, jr, jalr,
+            // multiply/divide and HI/LO moves)
             0 => {
+                match self.funct {
+                    0b001100 if self.syscall_mode == SyscallMode::Exception => {
+                        return Err(ErrorKind::Syscall)
+                    }
+                    0b001101 => return Err(ErrorKind::Breakpoint),
+                    _ => (),
+                }
+
                 self.signals.alu_op = AluOp::UseFunctField;
                 self.signals.alu_src = AluSrc::ReadRegister2;
+                // Shifts by a constant take their shift amount from shamt.
+                self.signals.alu_src_1 = match self.funct {
+                    0b000000 | 0b000010 | 0b000011 => AluSrc1::Shamt,
+                    _ => AluSrc1::ReadRegister1,
+                };
                 self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = match self.funct {
+                    0b010001 => HiLoWrite::WriteHi,
+                    0b010011 => HiLoWrite::WriteLo,
+                    0b011000..=0b011011 => HiLoWrite::WriteBoth,
+                    _ => HiLoWrite::NoWrite,
+                };
+                self.signals.jump = match self.funct {
+                    0b001000 | 0b001001 => Jump::YesJumpRegister,
+                    _ => Jump::NoJump,
+                };
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = match self.funct {
+                    0b001001 => MemToReg::UsePcPlusFour,
+                    0b010000 => MemToReg::UseHi,
+                    0b010010 => MemToReg::UseLo,
+                    _ => MemToReg::UseAlu,
+                };
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg3;
+                self.signals.reg_write = match self.funct {
+                    0b001000 | 0b001100 | 0b010001 | 0b010011 | 0b011000..=0b011011 => {
+                        RegWrite::NoWrite
+                    }
+                    _ => RegWrite::YesWrite,
+                };
+            }
+            // J-type instructions (j, jal)
+            2 | 3 => {
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::YesJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UsePcPlusFour;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::ReturnAddress;
+                self.signals.reg_write = match self.opcode {
+                    3 => RegWrite::YesWrite,
+                    _ => RegWrite::NoWrite,
+                };
+            }
+            // I-type ALU instructions (addi, addiu, slti, sltiu, andi, ori, xori, lui)
+            8..=15 => {
+                self.signals.alu_op = match self.opcode {
+                    8 => AluOp::Addition,
+                    9 => AluOp::AdditionUnsigned,
+                    10 => AluOp::SetOnLessThanSigned,
+                    11 => AluOp::SetOnLessThanUnsigned,
+                    12 => AluOp::And,
+                    13 => AluOp::Or,
+                    14 => AluOp::Xor,
+                    _ => AluOp::LeftShift16,
+                };
+                // The logical immediates are zero-extended rather than sign-extended.
+                self.signals.alu_src = match self.opcode {
+                    12..=14 => AluSrc::ZeroExtendedImmediate,
+                    _ => AluSrc::ExtendedImmediate,
+                };
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
                 self.signals.jump = Jump::NoJump;
                 self.signals.mem_read = MemRead::NoRead;
                 self.signals.mem_to_reg = MemToReg::UseAlu;
                 self.signals.mem_write = MemWrite::NoWrite;
                 self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
-                self.signals.reg_dst = RegDst::Reg3;
+                self.signals.reg_dst = RegDst::Reg2;
                 self.signals.reg_write = RegWrite::YesWrite;
             }
-            _ => error("Instruction not supported."),
+            // Load instructions (lb, lh, lw, lbu, lhu)
+            32 | 33 | 35 | 36 | 37 => {
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ExtendedImmediate;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::YesRead;
+                self.signals.mem_to_reg = MemToReg::UseMemory;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::YesWrite;
+            }
+            // Store instructions (sb, sh, sw)
+            40 | 41 | 43 => {
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ExtendedImmediate;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseAlu;
+                self.signals.mem_write = MemWrite::YesWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::NoWrite;
+            }
+            // Branch instructions comparing two registers (beq, bne)
+            4 | 5 => {
+                self.signals.alu_op = AluOp::SubtractionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::YesBranch;
+                self.signals.branch_type = match self.opcode {
+                    4 => BranchType::OnEqual,
+                    _ => BranchType::OnNotEqual,
+                };
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseAlu;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::NoWrite;
+            }
+            // Branch instructions comparing against zero (blez, bgtz)
+            6 | 7 => {
+                self.signals.alu_op = AluOp::SubtractionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::YesBranch;
+                self.signals.branch_type = match self.opcode {
+                    6 => BranchType::OnLessThanOrEqualToZero,
+                    _ => BranchType::OnGreaterThanZero,
+                };
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseAlu;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::NoWrite;
+            }
+            // REGIMM instructions (bltz, bgez, bltzal, bgezal), selected by rt
+            1 => {
+                self.signals.branch_type = match self.rt {
+                    0b00000 | 0b10000 => BranchType::OnLessThanZero,
+                    0b00001 | 0b10001 => BranchType::OnGreaterThanOrEqualToZero,
+                    _ => return Err(ErrorKind::ReservedInstruction),
+                };
+                // The "and link" variants write the return address whether
+                // or not the branch is taken.
+                self.signals.reg_write = match self.rt {
+                    0b10000 | 0b10001 => RegWrite::YesWrite,
+                    _ => RegWrite::NoWrite,
+                };
+                self.signals.alu_op = AluOp::SubtractionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::YesBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UsePcPlusFour;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::ReturnAddress;
+            }
+            // Coprocessor 1 instructions (mfc1, mtc1, cfc1, ctc1, bc1t, bc1f,
+            // arithmetic, conversions and compares), selected by rs
+            17 => {
+                // The rs field either selects a move or branch, or gives the
+                // format of an arithmetic or compare instruction.
+                let is_move_from = self.rs == 0b00000 || self.rs == 0b00010;
+                let is_mtc1 = self.rs == 0b00100;
+                let is_ctc1 = self.rs == 0b00110;
+                let is_branch = self.rs == 0b01000;
+                let is_compare =
+                    !(is_move_from || is_mtc1 || is_ctc1 || is_branch) && self.funct >= 0b110000;
+                // The branch-likely forms, bc1tl and bc1fl, are not implemented.
+                if is_branch && self.rt & 0b10 != 0 {
+                    return Err(ErrorKind::ReservedInstruction);
+                }
+
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = if is_branch {
+                    Branch::YesBranch
+                } else {
+                    Branch::NoBranch
+                };
+                // The low bit of rt selects bc1t over bc1f.
+                self.signals.branch_type = match self.rt & 1 {
+                    1 => BranchType::OnFpuConditionTrue,
+                    _ => BranchType::OnFpuConditionFalse,
+                };
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = if is_compare {
+                    FpuCcWrite::YesWrite
+                } else {
+                    FpuCcWrite::NoWrite
+                };
+                self.signals.fpu_reg_dst = if is_mtc1 {
+                    FpuRegDst::Fs
+                } else if is_ctc1 {
+                    FpuRegDst::ControlRegister
+                } else {
+                    FpuRegDst::Fd
+                };
+                self.signals.fpu_reg_write = if is_move_from || is_branch || is_compare {
+                    FpuRegWrite::NoWrite
+                } else {
+                    FpuRegWrite::YesWrite
+                };
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseFloatingPointUnit;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = if is_move_from {
+                    RegWrite::YesWrite
+                } else {
+                    RegWrite::NoWrite
+                };
+            }
+            // Floating-point loads (lwc1, ldc1)
+            49 | 53 => {
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ExtendedImmediate;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Ft;
+                self.signals.fpu_reg_write = FpuRegWrite::YesWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::YesRead;
+                self.signals.mem_to_reg = MemToReg::UseMemory;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::NoWrite;
+            }
+            // Floating-point stores (swc1, sdc1)
+            57 | 61 => {
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ExtendedImmediate;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = Cp0Write::NoWrite;
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Ft;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = Jump::NoJump;
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseAlu;
+                self.signals.mem_write = MemWrite::YesWrite;
+                self.signals.mem_write_src = MemWriteSrc::FloatingPointUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = RegWrite::NoWrite;
+            }
+            // Coprocessor 0 instructions (mfc0, mtc0, eret), selected by rs
+            16 => {
+                let is_mfc0 = self.rs == 0b00000;
+                let is_mtc0 = self.rs == 0b00100;
+                let is_eret = self.rs == 0b10000 && self.funct == 0b011000;
+                if !(is_mfc0 || is_mtc0 || is_eret) {
+                    return Err(ErrorKind::ReservedInstruction);
+                }
+
+                self.signals.alu_op = AluOp::AdditionUnsigned;
+                self.signals.alu_src = AluSrc::ReadRegister2;
+                self.signals.alu_src_1 = AluSrc1::ReadRegister1;
+                self.signals.branch = Branch::NoBranch;
+                self.signals.cp0_write = if is_mtc0 {
+                    Cp0Write::YesWrite
+                } else {
+                    Cp0Write::NoWrite
+                };
+                self.signals.fpu_cc_write = FpuCcWrite::NoWrite;
+                self.signals.fpu_reg_dst = FpuRegDst::Fd;
+                self.signals.fpu_reg_write = FpuRegWrite::NoWrite;
+                self.signals.hi_lo_write = HiLoWrite::NoWrite;
+                self.signals.jump = if is_eret {
+                    Jump::YesExceptionReturn
+                } else {
+                    Jump::NoJump
+                };
+                self.signals.mem_read = MemRead::NoRead;
+                self.signals.mem_to_reg = MemToReg::UseCoprocessor0;
+                self.signals.mem_write = MemWrite::NoWrite;
+                self.signals.mem_write_src = MemWriteSrc::PrimaryUnit;
+                self.signals.reg_dst = RegDst::Reg2;
+                self.signals.reg_write = if is_mfc0 {
+                    RegWrite::YesWrite
+                } else {
+                    RegWrite::NoWrite
+                };
+            }
+            _ => return Err(ErrorKind::ReservedInstruction),
         }
+
+        Ok(())
     }
 
     fn read_registers(&mut self) {
@@ -189,9 +698,14 @@ impl MipsDatapath {
 
         self.read_data_1 = self.registers.gpr[reg1];
         self.read_data_2 = self.registers.gpr[reg2];
+
+        // Floating-point instructions read fs from the rd field and ft
+        // from the rt field.
+        self.fpu_read_data_1 = self.registers.fpr[self.rd as usize];
+        self.fpu_read_data_2 = self.registers.fpr[reg2];
     }
 
-    fn set_alu_control(&mut self) {
+    fn set_alu_control(&mut self) -> Result<(), ErrorKind> {
         self.signals.alu_control = match self.signals.alu_op {
             AluOp::Addition => AluControl::Addition,
             AluOp::Subtraction => AluControl::Subtraction,
@@ -200,37 +714,252 @@ impl MipsDatapath {
             AluOp::And => AluControl::And,
             AluOp::Or => AluControl::Or,
             AluOp::LeftShift16 => AluControl::LeftShift16,
+            AluOp::Xor => AluControl::Xor,
+            AluOp::AdditionUnsigned => AluControl::AdditionUnsigned,
+            AluOp::SubtractionUnsigned => AluControl::SubtractionUnsigned,
             AluOp::UseFunctField => match self.funct {
+                0b000000 | 0b000100 => AluControl::ShiftLeftLogical,
+                0b000010 | 0b000110 => AluControl::ShiftRightLogical,
+                0b000011 | 0b000111 => AluControl::ShiftRightArithmetic,
+                // jr, jalr, syscall and the multiply/divide unit do not use the ALU.
+                0b001000 | 0b001001 | 0b001100 | 0b010000..=0b010011 | 0b011000..=0b011011 => {
+                    AluControl::AdditionUnsigned
+                }
                 0b100000 => AluControl::Addition,
+                0b100001 => AluControl::AdditionUnsigned,
                 0b100010 => AluControl::Subtraction,
+                0b100011 => AluControl::SubtractionUnsigned,
                 0b100100 => AluControl::And,
                 0b100101 => AluControl::Or,
+                0b100110 => AluControl::Xor,
+                0b100111 => AluControl::Nor,
                 0b101010 => AluControl::SetOnLessThanSigned,
                 0b101011 => AluControl::SetOnLessThanUnsigned,
-                _ => {
-                    error("Unsupported funct");
-                    AluControl::Addition // Stub
-                }
+                _ => return Err(ErrorKind::UnsupportedFunct(self.funct)),
             },
         };
+
+        Ok(())
     }
 
     fn alu(&mut self) {
-        let input1 = self.read_data_1;
+        let input1 = match self.signals.alu_src_1 {
+            AluSrc1::ReadRegister1 => self.read_data_1,
+            AluSrc1::Shamt => self.shamt as u64,
+        };
         let input2 = match self.signals.alu_src {
             AluSrc::ReadRegister2 => self.read_data_2,
             AluSrc::ExtendedImmediate => self.sign_extend,
+            AluSrc::ZeroExtendedImmediate => self.imm as u64,
         };
 
+        self.alu_overflow = false;
         self.alu_result = match self.signals.alu_control {
-            AluControl::Addition => input1 + input2,
-            AluControl::Subtraction => input1 - input2,
+            // Arithmetic is performed on the low 32 bits and sign-extended.
+            // Only the signed variants report overflow.
+            AluControl::Addition => {
+                let (result, overflow) = (input1 as i32).overflowing_add(input2 as i32);
+                self.alu_overflow = overflow;
+                result as u64
+            }
+            AluControl::AdditionUnsigned => (input1 as i32).wrapping_add(input2 as i32) as u64,
+            AluControl::Subtraction => {
+                let (result, overflow) = (input1 as i32).overflowing_sub(input2 as i32);
+                self.alu_overflow = overflow;
+                result as u64
+            }
+            AluControl::SubtractionUnsigned => (input1 as i32).wrapping_sub(input2 as i32) as u64,
             AluControl::SetOnLessThanSigned => ((input1 as i64) < (input2 as i64)) as u64,
             AluControl::SetOnLessThanUnsigned => (input1 < input2) as u64,
             AluControl::And => input1 & input2,
             AluControl::Or => input1 | input2,
             AluControl::LeftShift16 => input2 << 16,
             AluControl::Not => !input1,
+            AluControl::Xor => input1 ^ input2,
+            AluControl::Nor => !(input1 | input2),
+            // Shifts operate on the low 32 bits of input 2 by the low five
+            // bits of input 1, sign-extending the 32-bit result.
+            AluControl::ShiftLeftLogical => ((input2 as u32) << (input1 & 0b11111)) as i32 as u64,
+            AluControl::ShiftRightLogical => ((input2 as u32) >> (input1 & 0b11111)) as i32 as u64,
+            AluControl::ShiftRightArithmetic => ((input2 as i32) >> (input1 & 0b11111)) as u64,
+        };
+
+        self.alu_zero = self.alu_result == 0;
+    }
+
+    fn multiply_divide(&mut self) {
+        if self.signals.hi_lo_write == HiLoWrite::NoWrite {
+            return;
+        }
+
+        let rs = self.read_data_1;
+        let rt = self.read_data_2;
+
+        // Results are 32-bit halves, sign-extended into HI and LO.
+        (self.hi_result, self.lo_result) = match self.funct {
+            // mthi
+            0b010001 => (rs, self.registers.lo),
+            // mtlo
+            0b010011 => (self.registers.hi, rs),
+            // mult
+            0b011000 => {
+                let product = (rs as i32 as i64) * (rt as i32 as i64);
+                ((product >> 32) as i32 as u64, product as i32 as u64)
+            }
+            // multu
+            0b011001 => {
+                let product = (rs as u32 as u64) * (rt as u32 as u64);
+                ((product >> 32) as i32 as u64, product as i32 as u64)
+            }
+            // Dividing by zero leaves the result undefined and does not trap,
+            // so HI and LO are left as they are.
+            0b011010 | 0b011011 if rt as u32 == 0 => (self.registers.hi, self.registers.lo),
+            // div
+            0b011010 => {
+                let (dividend, divisor) = (rs as i32, rt as i32);
+                (
+                    dividend.wrapping_rem(divisor) as u64,
+                    dividend.wrapping_div(divisor) as u64,
+                )
+            }
+            // divu
+            _ => {
+                let (dividend, divisor) = (rs as u32, rt as u32);
+                (
+                    (dividend % divisor) as i32 as u64,
+                    (dividend / divisor) as i32 as u64,
+                )
+            }
+        };
+    }
+
+    fn floating_point_unit(&mut self) -> Result<(), ErrorKind> {
+        if self.opcode != 17 {
+            return Ok(());
+        }
+
+        let mode = RoundingMode::from_fcsr(self.registers.fcsr);
+        let mut flags = 0;
+
+        self.fpu_result = match self.rs {
+            // mfc1: the low word of fs, sign-extended into rt
+            0b00000 => self.fpu_read_data_1 as i32 as u64,
+            // cfc1: the control register fs, sign-extended into rt
+            0b00010 => self.registers.get_control_register(self.rd).unwrap_or(0) as i32 as u64,
+            // mtc1, ctc1: the low word of rt into fs
+            0b00100 | 0b00110 => self.read_data_2 as u32 as u64,
+            // bc1t, bc1f: resolved when setting the PC
+            0b01000 => 0,
+            // c.cond.fmt: whether the condition holds
+            fmt if self.funct >= 0b110000 => {
+                let (result, compare_flags) =
+                    fpu::compare(self.funct, fmt, self.fpu_read_data_1, self.fpu_read_data_2)
+                        .ok_or(ErrorKind::ReservedInstruction)?;
+                flags = compare_flags;
+                result as u64
+            }
+            // movf.fmt, movt.fmt: the low bit of rt selects movt, and the
+            // upper three bits the condition code
+            fmt @ (fpu::FMT_SINGLE | fpu::FMT_DOUBLE) if self.funct == 0b010001 => {
+                let condition = self.registers.get_condition_code(self.rt >> 2);
+                self.conditional_move(fmt, condition == (self.rt & 1 == 1))
+            }
+            // movz.fmt, movn.fmt: test the general-purpose register rt
+            fmt @ (fpu::FMT_SINGLE | fpu::FMT_DOUBLE) if self.funct == 0b010010 => {
+                self.conditional_move(fmt, self.read_data_2 == 0)
+            }
+            fmt @ (fpu::FMT_SINGLE | fpu::FMT_DOUBLE) if self.funct == 0b010011 => {
+                self.conditional_move(fmt, self.read_data_2 != 0)
+            }
+            fmt => {
+                let (result, arithmetic_flags) = fpu::arithmetic(
+                    self.funct,
+                    fmt,
+                    self.fpu_read_data_1,
+                    self.fpu_read_data_2,
+                    mode,
+                )
+                .ok_or(ErrorKind::ReservedInstruction)?;
+                flags = arithmetic_flags;
+                result
+            }
+        };
+
+        // Every arithmetic instruction replaces the cause field. Enabled
+        // exceptions trap without writing a result, while the rest
+        // accumulate in the sticky flags.
+        if !matches!(self.rs, 0b00000 | 0b00010 | 0b00100 | 0b00110 | 0b01000) {
+            let flags = flags as u64;
+            let enables =
+                (self.registers.fcsr >> fpu::FCSR_ENABLES_SHIFT) & fpu::EXCEPTION_MASK as u64;
+
+            self.registers.fcsr &= !((fpu::EXCEPTION_MASK as u64) << fpu::FCSR_CAUSE_SHIFT);
+            self.registers.fcsr |= flags << fpu::FCSR_CAUSE_SHIFT;
+
+            if flags & enables != 0 {
+                return Err(ErrorKind::FloatingPointException);
+            }
+            self.registers.fcsr |= flags << fpu::FCSR_FLAGS_SHIFT;
+        }
+
+        Ok(())
+    }
+
+    /// The result of a conditional move: fs if the condition holds, or
+    /// else the current value of fd.
+    fn conditional_move(&self, fmt: u32, condition: bool) -> u64 {
+        let value = if condition {
+            self.fpu_read_data_1
+        } else {
+            self.registers.fpr[self.shamt as usize]
+        };
+
+        match fmt {
+            fpu::FMT_SINGLE => value as u32 as u64,
+            _ => value,
+        }
+    }
+
+    fn memory_read(&mut self) -> Result<(), ErrorKind> {
+        let address = self.alu_result;
+
+        // The access width and extension are given by the opcode.
+        self.memory_data = match self.opcode {
+            // lb
+            32 => self.memory.load_byte(address)? as i8 as u64,
+            // lh
+            33 => self.memory.load_half(address)? as i16 as u64,
+            // lbu
+            36 => self.memory.load_byte(address)? as u64,
+            // lhu
+            37 => self.memory.load_half(address)? as u64,
+            // lwc1
+            49 => self.memory.load_word(address)? as u64,
+            // ldc1
+            53 => self.memory.load_doubleword(address)?,
+            // lw
+            _ => self.memory.load_word(address)? as i32 as u64,
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(&mut self) -> Result<(), ErrorKind> {
+        let address = self.alu_result;
+        let data = match self.signals.mem_write_src {
+            MemWriteSrc::PrimaryUnit => self.read_data_2,
+            MemWriteSrc::FloatingPointUnit => self.fpu_read_data_2,
+        };
+
+        match self.opcode {
+            // sb
+            40 => self.memory.store_byte(address, data as u8),
+            // sh
+            41 => self.memory.store_half(address, data as u16),
+            // sdc1
+            61 => self.memory.store_doubleword(address, data),
+            // sw, swc1
+            _ => self.memory.store_word(address, data as u32),
         }
     }
 
@@ -238,8 +967,50 @@ impl MipsDatapath {
         self.data_result = match self.signals.mem_to_reg {
             MemToReg::UseAlu => self.alu_result,
             MemToReg::UseMemory => self.memory_data,
+            MemToReg::UsePcPlusFour => self.registers.pc.wrapping_add(4),
+            MemToReg::UseHi => self.registers.hi,
+            MemToReg::UseLo => self.registers.lo,
+            MemToReg::UseCoprocessor0 => self.coprocessor0.get(self.rd).unwrap_or(0),
+            MemToReg::UseFloatingPointUnit => self.fpu_result,
         };
 
+        // Compares select their condition code with the upper three bits
+        // of the fd field.
+        if self.signals.fpu_cc_write == FpuCcWrite::YesWrite {
+            self.registers
+                .set_condition_code(self.shamt >> 2, self.fpu_result != 0);
+        }
+
+        if self.signals.fpu_reg_write == FpuRegWrite::YesWrite {
+            match self.signals.fpu_reg_dst {
+                FpuRegDst::Ft => self.registers.fpr[self.rt as usize] = self.data_result,
+                FpuRegDst::Fs => self.registers.fpr[self.rd as usize] = self.data_result,
+                FpuRegDst::Fd => self.registers.fpr[self.shamt as usize] = self.data_result,
+                FpuRegDst::ControlRegister => self
+                    .registers
+                    .set_control_register(self.rd, self.data_result),
+            }
+        }
+
+        if self.signals.cp0_write == Cp0Write::YesWrite {
+            self.coprocessor0.set(self.rd, self.read_data_2);
+        }
+
+        // Returning from an exception leaves the exception level.
+        if self.signals.jump == Jump::YesExceptionReturn {
+            self.coprocessor0.status &= !STATUS_EXL;
+        }
+
+        match self.signals.hi_lo_write {
+            HiLoWrite::NoWrite => (),
+            HiLoWrite::WriteHi => self.registers.hi = self.hi_result,
+            HiLoWrite::WriteLo => self.registers.lo = self.lo_result,
+            HiLoWrite::WriteBoth => {
+                self.registers.hi = self.hi_result;
+                self.registers.lo = self.lo_result;
+            }
+        }
+
         if self.signals.reg_write == RegWrite::NoWrite {
             return;
         }
@@ -247,12 +1018,51 @@ impl MipsDatapath {
         let destination = match self.signals.reg_dst {
             RegDst::Reg2 => self.rt as usize,
             RegDst::Reg3 => self.rd as usize,
+            RegDst::ReturnAddress => 31,
         };
 
+        // $zero is hardwired and ignores writes.
+        if destination == 0 {
+            return;
+        }
+
         self.registers.gpr[destination] = self.data_result;
     }
 
     fn set_pc(&mut self) {
-        self.registers.pc += 4;
+        let pc_plus_four = self.registers.pc.wrapping_add(4);
+
+        self.registers.pc = match self.signals.jump {
+            // The 26-bit target replaces all but the upper four bits of PC + 4.
+            Jump::YesJump => (pc_plus_four & !0x0FFF_FFFF) | ((self.addr as u64) << 2),
+            Jump::YesJumpRegister => self.read_data_1,
+            Jump::YesExceptionReturn => self.coprocessor0.epc,
+            Jump::NoJump => {
+                if self.signals.branch == Branch::YesBranch && self.branch_taken() {
+                    pc_plus_four.wrapping_add(self.sign_extend << 2)
+                } else {
+                    pc_plus_four
+                }
+            }
+        };
+    }
+
+    fn branch_taken(&self) -> bool {
+        // Register-register comparisons use the ALU's zero output, while
+        // comparisons against zero test rs directly.
+        let rs = self.read_data_1 as i64;
+
+        match self.signals.branch_type {
+            BranchType::OnEqual => self.alu_zero,
+            BranchType::OnNotEqual => !self.alu_zero,
+            BranchType::OnLessThanZero => rs < 0,
+            BranchType::OnLessThanOrEqualToZero => rs <= 0,
+            BranchType::OnGreaterThanZero => rs > 0,
+            BranchType::OnGreaterThanOrEqualToZero => rs >= 0,
+            // bc1t and bc1f select their condition code with the upper
+            // three bits of the rt field.
+            BranchType::OnFpuConditionTrue => self.registers.get_condition_code(self.rt >> 2),
+            BranchType::OnFpuConditionFalse => !self.registers.get_condition_code(self.rt >> 2),
+        }
     }
 }
@@ -0,0 +1,129 @@
+use super::{error::ErrorKind, memory::Endianness};
+use std::collections::HashMap;
+
+const BYTES_4K: usize = 4 * 1024;
+
+/// RAM is allocated in pages of this many bytes as they are first written.
+pub const PAGE_SIZE: usize = BYTES_4K;
+
+/// Something attached to the memory bus that responds to loads and stores
+/// within the address range it claims.
+///
+/// Accesses are 1, 2, 4 or 8 bytes wide and naturally aligned. Offsets are
+/// relative to the start of the claimed range, and values are in the low
+/// `size` bytes. A device that does not respond at an offset should return
+/// a bus error; the bus fills in the full address.
+pub trait Device {
+    fn read(&mut self, offset: u64, size: u64) -> Result<u64, ErrorKind>;
+    fn write(&mut self, offset: u64, size: u64, value: u64) -> Result<(), ErrorKind>;
+
+    /// The interrupt lines the device is asserting, one bit for each of
+    /// the eight interrupt-pending bits in the Cause register.
+    fn interrupts(&self) -> u8 {
+        0
+    }
+}
+
+/// Sparse RAM. Pages are allocated the first time they are written, and
+/// unallocated memory reads as zero.
+pub struct Ram {
+    endianness: Endianness,
+    /// Offsets at or above this limit do not respond.
+    size: Option<u64>,
+    pages: HashMap<u64, Box<[u8; PAGE_SIZE]>>,
+}
+
+impl Ram {
+    pub fn new(endianness: Endianness, size: Option<u64>) -> Self {
+        Self {
+            endianness,
+            size,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// The number of pages that have been allocated so far.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn check_bounds(&self, offset: u64, size: u64) -> Result<(), ErrorKind> {
+        match self.size {
+            Some(limit) if offset + (size - 1) >= limit => {
+                Err(ErrorKind::BusError { address: offset })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Device for Ram {
+    fn read(&mut self, offset: u64, size: u64) -> Result<u64, ErrorKind> {
+        self.check_bounds(offset, size)?;
+
+        // An aligned access never crosses a page.
+        let Some(page) = self.pages.get(&(offset / PAGE_SIZE as u64)) else {
+            return Ok(0);
+        };
+        let start = offset as usize % PAGE_SIZE;
+        let bytes = &page[start..start + size as usize];
+
+        // Widen to a doubleword, keeping the value in the low bytes.
+        let mut buffer = [0; 8];
+        Ok(match self.endianness {
+            Endianness::Big => {
+                buffer[8 - bytes.len()..].copy_from_slice(bytes);
+                u64::from_be_bytes(buffer)
+            }
+            Endianness::Little => {
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                u64::from_le_bytes(buffer)
+            }
+        })
+    }
+
+    fn write(&mut self, offset: u64, size: u64, value: u64) -> Result<(), ErrorKind> {
+        self.check_bounds(offset, size)?;
+
+        let page = self
+            .pages
+            .entry(offset / PAGE_SIZE as u64)
+            .or_insert_with(|| Box::new([0; PAGE_SIZE]));
+        let start = offset as usize % PAGE_SIZE;
+        let size = size as usize;
+
+        match self.endianness {
+            Endianness::Big => {
+                page[start..start + size].copy_from_slice(&value.to_be_bytes()[8 - size..])
+            }
+            Endianness::Little => {
+                page[start..start + size].copy_from_slice(&value.to_le_bytes()[..size])
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Ram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut page_numbers: Vec<&u64> = self.pages.keys().collect();
+        page_numbers.sort();
+
+        for page_number in page_numbers {
+            let base = page_number * PAGE_SIZE as u64;
+            for (i, word) in self.pages[page_number].chunks(4).enumerate() {
+                write!(f, "{:#010x}   ", base + i as u64 * 4)?;
+                for (j, value) in word.iter().enumerate() {
+                    write!(f, "{:08b}", value)?;
+                    if j < 3 {
+                        write!(f, " ")?;
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
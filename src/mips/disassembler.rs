@@ -0,0 +1,354 @@
+// Turn instruction words back into assembly, in the syntax the assembler
+// accepts, except that a branch target without a label is shown as an
+// absolute address rather than an offset. Each instruction is shown as the
+// real instruction it is, so an expanded pseudo-instruction appears as its
+// expansion; only the all-zero word is shown as `nop`.
+
+use super::error::ErrorKind;
+use super::fpu::{CONDITIONS, FMT_DOUBLE, FMT_SINGLE, FMT_WORD};
+use super::memory::Memory;
+use super::registers::GPR_NAMES;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// How general-purpose registers are written.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum RegisterNames {
+    /// By ABI name, such as `$t0`.
+    #[default]
+    Abi,
+    /// By number, such as `$8`.
+    Numeric,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Disassembler {
+    pub register_names: RegisterNames,
+    // Labels to show in place of the addresses they name.
+    labels: BTreeMap<u64, String>,
+}
+
+impl Disassembler {
+    pub fn new(register_names: RegisterNames) -> Self {
+        Self {
+            register_names,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Show branch and jump targets that have a label, such as those in an
+    /// assembled program's symbol table, by name. Where several labels
+    /// name one address, the first alphabetically is used.
+    pub fn with_symbols(mut self, symbols: &HashMap<String, u64>) -> Self {
+        for (name, address) in symbols {
+            let label = self.labels.entry(*address).or_insert_with(|| name.clone());
+            if name < label {
+                *label = name.clone();
+            }
+        }
+        self
+    }
+
+    /// The text of the instruction placed at an address, or `None` if its
+    /// encoding is reserved.
+    pub fn disassemble(&self, instruction: u32, address: u64) -> Option<String> {
+        let fields = Fields::new(instruction);
+        let (rs, rt) = (self.gpr(fields.rs), self.gpr(fields.rt));
+        let signed = fields.immediate as u16 as i16;
+        let branch_target = self.target(
+            address
+                .wrapping_add(4)
+                .wrapping_add((signed as i64 * 4) as u64),
+        );
+
+        let text = match fields.opcode {
+            0 => return self.special(&fields),
+            1 => {
+                let mnemonic = match fields.rt {
+                    0b00000 => "bltz",
+                    0b00001 => "bgez",
+                    0b10000 => "bltzal",
+                    0b10001 => "bgezal",
+                    _ => return None,
+                };
+                format!("{mnemonic} {rs}, {branch_target}")
+            }
+            2 | 3 => {
+                let mnemonic = if fields.opcode == 2 { "j" } else { "jal" };
+                let region = address.wrapping_add(4) & 0xF000_0000;
+                let target = region | ((instruction as u64 & 0x03FF_FFFF) << 2);
+                format!("{mnemonic} {}", self.target(target))
+            }
+            4 | 5 => {
+                let mnemonic = if fields.opcode == 4 { "beq" } else { "bne" };
+                format!("{mnemonic} {rs}, {rt}, {branch_target}")
+            }
+            6 | 7 => {
+                let mnemonic = if fields.opcode == 6 { "blez" } else { "bgtz" };
+                format!("{mnemonic} {rs}, {branch_target}")
+            }
+            8..=11 => {
+                let mnemonic = ["addi", "addiu", "slti", "sltiu"][fields.opcode as usize - 8];
+                format!("{mnemonic} {rt}, {rs}, {signed}")
+            }
+            // The logical immediates are zero-extended, so show them in hex.
+            12..=14 => {
+                let mnemonic = ["andi", "ori", "xori"][fields.opcode as usize - 12];
+                format!("{mnemonic} {rt}, {rs}, {:#x}", fields.immediate)
+            }
+            15 => format!("lui {rt}, {:#x}", fields.immediate),
+            16 => return self.coprocessor0(&fields),
+            17 => return self.coprocessor1(&fields, address),
+            32 | 33 | 35 | 36 | 37 | 40 | 41 | 43 => {
+                let mnemonic = match fields.opcode {
+                    32 => "lb",
+                    33 => "lh",
+                    35 => "lw",
+                    36 => "lbu",
+                    37 => "lhu",
+                    40 => "sb",
+                    41 => "sh",
+                    _ => "sw",
+                };
+                format!("{mnemonic} {rt}, {signed}({rs})")
+            }
+            49 | 53 | 57 | 61 => {
+                let mnemonic = match fields.opcode {
+                    49 => "lwc1",
+                    53 => "ldc1",
+                    57 => "swc1",
+                    _ => "sdc1",
+                };
+                format!("{mnemonic} $f{}, {signed}({rs})", fields.rt)
+            }
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    /// A listing of `count` words of memory from `start`: each word's
+    /// address, value and text, with any label on a line of its own.
+    pub fn listing(
+        &self,
+        memory: &mut Memory,
+        start: u64,
+        count: usize,
+    ) -> Result<String, ErrorKind> {
+        let mut listing = String::new();
+
+        for i in 0..count as u64 {
+            let address = start + i * 4;
+            let word = memory.load_word(address)?;
+
+            if let Some(label) = self.labels.get(&address) {
+                writeln!(listing, "{label}:").unwrap();
+            }
+            let text = match self.disassemble(word, address) {
+                Some(text) => text,
+                None => format!(".word {word:#010x}  # reserved"),
+            };
+            writeln!(listing, "{address:#010x}  {word:08x}  {text}").unwrap();
+        }
+
+        Ok(listing)
+    }
+
+    fn special(&self, fields: &Fields) -> Option<String> {
+        let (rs, rt, rd) = (
+            self.gpr(fields.rs),
+            self.gpr(fields.rt),
+            self.gpr(fields.rd),
+        );
+        let shamt = fields.shamt;
+
+        let text = match fields.funct {
+            0x00 if fields.instruction == 0 => "nop".to_string(),
+            0x00 => format!("sll {rd}, {rt}, {shamt}"),
+            0x02 => format!("srl {rd}, {rt}, {shamt}"),
+            0x03 => format!("sra {rd}, {rt}, {shamt}"),
+            0x04 => format!("sllv {rd}, {rt}, {rs}"),
+            0x06 => format!("srlv {rd}, {rt}, {rs}"),
+            0x07 => format!("srav {rd}, {rt}, {rs}"),
+            0x08 => format!("jr {rs}"),
+            // The link register is left out when it is $ra.
+            0x09 if fields.rd == 31 => format!("jalr {rs}"),
+            0x09 => format!("jalr {rd}, {rs}"),
+            0x0C => "syscall".to_string(),
+            0x0D => "break".to_string(),
+            0x10 => format!("mfhi {rd}"),
+            0x11 => format!("mthi {rs}"),
+            0x12 => format!("mflo {rd}"),
+            0x13 => format!("mtlo {rs}"),
+            0x18..=0x1B => {
+                let mnemonic = ["mult", "multu", "div", "divu"][fields.funct as usize - 0x18];
+                format!("{mnemonic} {rs}, {rt}")
+            }
+            0x20..=0x27 | 0x2A | 0x2B => {
+                let mnemonic = match fields.funct {
+                    0x20 => "add",
+                    0x21 => "addu",
+                    0x22 => "sub",
+                    0x23 => "subu",
+                    0x24 => "and",
+                    0x25 => "or",
+                    0x26 => "xor",
+                    0x27 => "nor",
+                    0x2A => "slt",
+                    _ => "sltu",
+                };
+                format!("{mnemonic} {rd}, {rs}, {rt}")
+            }
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    // Coprocessor 0 registers are written by number.
+    fn coprocessor0(&self, fields: &Fields) -> Option<String> {
+        let rt = self.gpr(fields.rt);
+
+        let text = match fields.rs {
+            0b00000 => format!("mfc0 {rt}, ${}", fields.rd),
+            0b00100 => format!("mtc0 {rt}, ${}", fields.rd),
+            0b10000 if fields.funct == 0b011000 => "eret".to_string(),
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    fn coprocessor1(&self, fields: &Fields, address: u64) -> Option<String> {
+        let rt = self.gpr(fields.rt);
+        let (fs, ft, fd) = (fields.rd, fields.rt, fields.shamt);
+
+        let text = match fields.rs {
+            0b00000 => format!("mfc1 {rt}, $f{fs}"),
+            0b00010 => format!("cfc1 {rt}, ${fs}"),
+            0b00100 => format!("mtc1 {rt}, $f{fs}"),
+            0b00110 => format!("ctc1 {rt}, ${fs}"),
+            // The condition code is left out when it is 0. The likely forms
+            // are not supported.
+            0b01000 if fields.rt & 0b10 == 0 => {
+                let mnemonic = if fields.rt & 1 == 1 { "bc1t" } else { "bc1f" };
+                let offset = fields.immediate as u16 as i16 as i64 * 4;
+                let target = self.target(address.wrapping_add(4).wrapping_add(offset as u64));
+                match fields.rt >> 2 {
+                    0 => format!("{mnemonic} {target}"),
+                    cc => format!("{mnemonic} {cc}, {target}"),
+                }
+            }
+            _ => return self.floating_point(fields, fs, ft, fd),
+        };
+
+        Some(text)
+    }
+
+    fn floating_point(&self, fields: &Fields, fs: u32, ft: u32, fd: u32) -> Option<String> {
+        let fmt = match fields.rs {
+            FMT_SINGLE => "s",
+            FMT_DOUBLE => "d",
+            FMT_WORD => "w",
+            _ => return None,
+        };
+
+        // Only conversions from words take the word format, and a value
+        // cannot be converted to its own format.
+        let conversion = match fields.funct {
+            0x20 => Some(FMT_SINGLE),
+            0x21 => Some(FMT_DOUBLE),
+            _ => None,
+        };
+        if (fields.rs == FMT_WORD && conversion.is_none()) || conversion == Some(fields.rs) {
+            return None;
+        }
+
+        let text = match fields.funct {
+            0x00..=0x03 => {
+                let operation = ["add", "sub", "mul", "div"][fields.funct as usize];
+                format!("{operation}.{fmt} $f{fd}, $f{fs}, $f{ft}")
+            }
+            0x04..=0x07 | 0x0C..=0x0F | 0x20 | 0x21 | 0x24 => {
+                let operation = match fields.funct {
+                    0x04 => "sqrt",
+                    0x05 => "abs",
+                    0x06 => "mov",
+                    0x07 => "neg",
+                    0x0C => "round.w",
+                    0x0D => "trunc.w",
+                    0x0E => "ceil.w",
+                    0x0F => "floor.w",
+                    0x20 => "cvt.s",
+                    0x21 => "cvt.d",
+                    _ => "cvt.w",
+                };
+                format!("{operation}.{fmt} $f{fd}, $f{fs}")
+            }
+            // Set in ft, the tf bit makes this movt; the condition code
+            // number sits two bits above it.
+            0x11 => {
+                let operation = if ft & 1 == 1 { "movt" } else { "movf" };
+                format!("{operation}.{fmt} $f{fd}, $f{fs}, {}", ft >> 2)
+            }
+            0x12 | 0x13 => {
+                let operation = if fields.funct == 0x12 { "movz" } else { "movn" };
+                format!("{operation}.{fmt} $f{fd}, $f{fs}, {}", self.gpr(ft))
+            }
+            // The condition code is held in the upper three bits of the fd
+            // field, and is left out when it is 0.
+            0x30..=0x3F => {
+                let condition = CONDITIONS[fields.funct as usize - 0x30];
+                match fd >> 2 {
+                    0 => format!("c.{condition}.{fmt} $f{fs}, $f{ft}"),
+                    cc => format!("c.{condition}.{fmt} {cc}, $f{fs}, $f{ft}"),
+                }
+            }
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    fn gpr(&self, number: u32) -> String {
+        match self.register_names {
+            RegisterNames::Abi => format!("${}", GPR_NAMES[number as usize]),
+            RegisterNames::Numeric => format!("${number}"),
+        }
+    }
+
+    // A branch or jump target, by label if it has one.
+    fn target(&self, address: u64) -> String {
+        match self.labels.get(&address) {
+            Some(label) => label.clone(),
+            None => format!("{address:#010x}"),
+        }
+    }
+}
+
+// The fields of an instruction word, for every format at once.
+struct Fields {
+    instruction: u32,
+    opcode: u32,
+    rs: u32,
+    rt: u32,
+    rd: u32,
+    shamt: u32,
+    funct: u32,
+    immediate: u32,
+}
+
+impl Fields {
+    fn new(instruction: u32) -> Self {
+        Self {
+            instruction,
+            opcode: instruction >> 26,
+            rs: (instruction >> 21) & 0x1F,
+            rt: (instruction >> 16) & 0x1F,
+            rd: (instruction >> 11) & 0x1F,
+            shamt: (instruction >> 6) & 0x1F,
+            funct: instruction & 0x3F,
+            immediate: instruction & 0xFFFF,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use super::memory::SegmentKind;
+use std::fmt;
+
+/// The reason an instruction could not be executed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// An enabled interrupt is pending.
+    Interrupt,
+    /// The opcode (or REGIMM rt field) does not name an instruction.
+    ReservedInstruction,
+    /// An R-type instruction used a funct field that is not implemented.
+    UnsupportedFunct(u32),
+    /// A signed add or subtract overflowed 32 bits.
+    IntegerOverflow,
+    /// A load or instruction fetch used an invalid address.
+    AddressErrorLoad { address: u64 },
+    /// A store used an invalid address.
+    AddressErrorStore { address: u64 },
+    /// No memory responded to an instruction fetch at the given address.
+    InstructionBusError { address: u64 },
+    /// No memory responded to a load or store at the given address.
+    BusError { address: u64 },
+    /// A `syscall` instruction was executed.
+    Syscall,
+    /// An emulated `syscall` requested a service that does not exist.
+    UnsupportedSyscall(u64),
+    /// A `break` instruction was executed.
+    Breakpoint,
+    /// A floating-point operation raised an exception enabled in the FCSR.
+    FloatingPointException,
+}
+
+impl ErrorKind {
+    /// The code stored in the Cause register when this error is taken as
+    /// an exception.
+    pub fn exception_code(&self) -> u32 {
+        match self {
+            ErrorKind::Interrupt => 0,
+            ErrorKind::AddressErrorLoad { .. } => 4,
+            ErrorKind::AddressErrorStore { .. } => 5,
+            ErrorKind::InstructionBusError { .. } => 6,
+            ErrorKind::BusError { .. } => 7,
+            ErrorKind::Syscall | ErrorKind::UnsupportedSyscall(_) => 8,
+            ErrorKind::Breakpoint => 9,
+            ErrorKind::ReservedInstruction | ErrorKind::UnsupportedFunct(_) => 10,
+            ErrorKind::IntegerOverflow => 12,
+            ErrorKind::FloatingPointException => 15,
+        }
+    }
+
+    /// The address an address or bus error is about, if any.
+    pub fn address(&self) -> Option<u64> {
+        match self {
+            ErrorKind::AddressErrorLoad { address }
+            | ErrorKind::AddressErrorStore { address }
+            | ErrorKind::InstructionBusError { address }
+            | ErrorKind::BusError { address } => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// The faulting address to record in BadVAddr, if any.
+    pub fn bad_address(&self) -> Option<u64> {
+        match self {
+            ErrorKind::AddressErrorLoad { address } | ErrorKind::AddressErrorStore { address } => {
+                Some(*address)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An error raised while executing an instruction, along with where it happened.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ExecutionError {
+    pub kind: ErrorKind,
+    /// Address of the faulting instruction.
+    pub pc: u64,
+    /// Raw instruction word, or zero if the instruction could not be fetched.
+    pub instruction: u32,
+    /// The segment holding the address of an address or bus error, if any.
+    pub segment: Option<SegmentKind>,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Interrupt => write!(f, "interrupt"),
+            ErrorKind::ReservedInstruction => write!(f, "reserved instruction"),
+            ErrorKind::UnsupportedFunct(funct) => write!(f, "unsupported funct {funct:#08b}"),
+            ErrorKind::IntegerOverflow => write!(f, "integer overflow"),
+            ErrorKind::AddressErrorLoad { address } => {
+                write!(f, "address error on load at {address:#010x}")
+            }
+            ErrorKind::AddressErrorStore { address } => {
+                write!(f, "address error on store at {address:#010x}")
+            }
+            ErrorKind::InstructionBusError { address } => {
+                write!(f, "bus error on instruction fetch at {address:#010x}")
+            }
+            ErrorKind::BusError { address } => write!(f, "bus error at {address:#010x}"),
+            ErrorKind::Syscall => write!(f, "syscall"),
+            ErrorKind::UnsupportedSyscall(code) => write!(f, "unsupported syscall {code}"),
+            ErrorKind::Breakpoint => write!(f, "breakpoint"),
+            ErrorKind::FloatingPointException => write!(f, "floating-point exception"),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.segment) {
+            (ErrorKind::AddressErrorLoad { address }, Some(segment)) => write!(
+                f,
+                "address error on load from {segment} segment at {address:#010x}"
+            )?,
+            (ErrorKind::AddressErrorStore { address }, Some(segment)) => write!(
+                f,
+                "address error on store to {segment} segment at {address:#010x}"
+            )?,
+            (ErrorKind::InstructionBusError { address }, Some(segment)) => write!(
+                f,
+                "bus error on instruction fetch from {segment} segment at {address:#010x}"
+            )?,
+            (ErrorKind::BusError { address }, Some(segment)) => {
+                write!(f, "bus error in {segment} segment at {address:#010x}")?
+            }
+            (kind, _) => write!(f, "{kind}")?,
+        }
+        write!(
+            f,
+            " (pc {:#010x}, instruction {:#010x})",
+            self.pc, self.instruction
+        )
+    }
+}
+
+impl std::error::Error for ExecutionError {}
@@ -0,0 +1,401 @@
+// Floating-point registers are modeled as 64 bits wide (the MIPS64 and
+// MIPS32r2 FR=1 model), so a double occupies a single register rather than
+// an even/odd pair. Single-precision values and words live in the low 32 bits.
+
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// The fmt field of a COP1 instruction.
+pub const FMT_SINGLE: u32 = 16;
+pub const FMT_DOUBLE: u32 = 17;
+pub const FMT_WORD: u32 = 20;
+
+/// The names of the sixteen c.cond.fmt conditions, in encoding order.
+pub const CONDITIONS: [&str; 16] = [
+    "f", "un", "eq", "ueq", "olt", "ult", "ole", "ule", "sf", "ngle", "seq", "ngl", "lt", "nge",
+    "le", "ngt",
+];
+
+/// Fields of the FCSR. The flag, enable and cause fields each hold the
+/// exception bits below, shifted into place.
+pub const FCSR_ROUNDING_MODE_MASK: u64 = 0b11;
+pub const FCSR_FLAGS_SHIFT: u64 = 2;
+pub const FCSR_ENABLES_SHIFT: u64 = 7;
+pub const FCSR_CAUSE_SHIFT: u64 = 12;
+
+/// IEEE-754 exceptions, in the bit order used by the FCSR fields.
+pub const INEXACT: u32 = 1 << 0;
+pub const UNDERFLOW: u32 = 1 << 1;
+pub const OVERFLOW: u32 = 1 << 2;
+pub const DIVIDE_BY_ZERO: u32 = 1 << 3;
+pub const INVALID: u32 = 1 << 4;
+pub const EXCEPTION_MASK: u32 = 0b11111;
+
+/// The value written by a conversion to word that is invalid.
+const INVALID_WORD: u32 = 0x7FFF_FFFF;
+
+/// The rounding mode held in the low two bits of the FCSR.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RoundingMode {
+    Nearest = 0,
+    TowardZero = 1,
+    TowardPositive = 2,
+    TowardNegative = 3,
+}
+
+impl RoundingMode {
+    pub fn from_fcsr(fcsr: u64) -> Self {
+        match fcsr & FCSR_ROUNDING_MODE_MASK {
+            0 => RoundingMode::Nearest,
+            1 => RoundingMode::TowardZero,
+            2 => RoundingMode::TowardPositive,
+            _ => RoundingMode::TowardNegative,
+        }
+    }
+}
+
+/// Perform a COP1 arithmetic or conversion instruction on the bit patterns
+/// of fs and ft, rounding as the given mode directs.
+///
+/// Returns the resulting bit pattern and any exceptions raised, or `None`
+/// if the funct/fmt combination is not an arithmetic instruction.
+pub fn arithmetic(
+    funct: u32,
+    fmt: u32,
+    fs: u64,
+    ft: u64,
+    mode: RoundingMode,
+) -> Option<(u64, u32)> {
+    match (funct, fmt) {
+        // abs, mov and neg only touch the sign bit and raise no exceptions.
+        (0b000101, FMT_SINGLE) => Some((fs as u32 as u64 & !(1 << 31), 0)),
+        (0b000101, FMT_DOUBLE) => Some((fs & !(1 << 63), 0)),
+        (0b000110, FMT_SINGLE) => Some((fs as u32 as u64, 0)),
+        (0b000110, FMT_DOUBLE) => Some((fs, 0)),
+        (0b000111, FMT_SINGLE) => Some((fs as u32 as u64 ^ (1 << 31), 0)),
+        (0b000111, FMT_DOUBLE) => Some((fs ^ (1 << 63), 0)),
+
+        // add, sub, mul, div, sqrt
+        (0b000000..=0b000100, FMT_SINGLE) => {
+            let (fs, ft) = (f32::from_bits(fs as u32), f32::from_bits(ft as u32));
+            let (result, flags) = operate(funct, fs, ft, mode);
+            Some((result.to_bits() as u64, flags))
+        }
+        (0b000000..=0b000100, FMT_DOUBLE) => {
+            let (fs, ft) = (f64::from_bits(fs), f64::from_bits(ft));
+            let (result, flags) = operate(funct, fs, ft, mode);
+            Some((result.to_bits(), flags))
+        }
+
+        // round.w, trunc.w, ceil.w, floor.w, cvt.w
+        (0b001100..=0b001111 | 0b100100, FMT_SINGLE | FMT_DOUBLE) => {
+            let mode = match funct {
+                0b001100 => RoundingMode::Nearest,
+                0b001101 => RoundingMode::TowardZero,
+                0b001110 => RoundingMode::TowardPositive,
+                0b001111 => RoundingMode::TowardNegative,
+                _ => mode,
+            };
+            let value = match fmt {
+                FMT_SINGLE => f32::from_bits(fs as u32) as f64,
+                _ => f64::from_bits(fs),
+            };
+            let (result, flags) = to_word(value, mode);
+            Some((result as u64, flags))
+        }
+
+        // cvt.s.d
+        (0b100000, FMT_DOUBLE) => {
+            let value = f64::from_bits(fs);
+            let nearest = value as f32;
+            let error = value
+                .partial_cmp(&(nearest as f64))
+                .unwrap_or(Ordering::Equal);
+            let (result, flags) = round(nearest, error, mode, value.is_nan(), value.is_finite());
+            Some((result.to_bits() as u64, flags | signalling(value)))
+        }
+        // cvt.s.w
+        (0b100000, FMT_WORD) => {
+            let value = fs as i32;
+            let nearest = value as f32;
+            let error = (value as i64).cmp(&(nearest as i64));
+            let (result, flags) = round(nearest, error, mode, false, true);
+            Some((result.to_bits() as u64, flags))
+        }
+        // cvt.d.s, which is always exact
+        (0b100001, FMT_SINGLE) => {
+            let value = f32::from_bits(fs as u32);
+            Some(((value as f64).to_bits(), signalling(value)))
+        }
+        // cvt.d.w, which is always exact
+        (0b100001, FMT_WORD) => Some(((fs as i32 as f64).to_bits(), 0)),
+
+        _ => None,
+    }
+}
+
+/// Perform a COP1 compare (c.cond.fmt) on the bit patterns of fs and ft.
+///
+/// Returns whether the condition holds and any exceptions raised, or `None`
+/// if the funct/fmt combination is not a compare.
+pub fn compare(funct: u32, fmt: u32, fs: u64, ft: u64) -> Option<(bool, u32)> {
+    if funct & 0b110000 != 0b110000 {
+        return None;
+    }
+
+    let (less, equal, unordered, invalid) = match fmt {
+        FMT_SINGLE => {
+            let (fs, ft) = (f32::from_bits(fs as u32), f32::from_bits(ft as u32));
+            let invalid = signalling(fs) | signalling(ft);
+            (fs < ft, fs == ft, fs.is_nan() || ft.is_nan(), invalid)
+        }
+        FMT_DOUBLE => {
+            let (fs, ft) = (f64::from_bits(fs), f64::from_bits(ft));
+            let invalid = signalling(fs) | signalling(ft);
+            (fs < ft, fs == ft, fs.is_nan() || ft.is_nan(), invalid)
+        }
+        _ => return None,
+    };
+
+    // The low three bits of the condition select which relations make it
+    // true. The fourth bit makes unordered operands signal invalid, as a
+    // signalling NaN does for every condition.
+    let condition = funct & 0b111;
+    let result = (condition & 0b100 != 0 && less)
+        || (condition & 0b010 != 0 && equal)
+        || (condition & 0b001 != 0 && unordered);
+    let flags = if funct & 0b1000 != 0 && unordered {
+        INVALID
+    } else {
+        invalid
+    };
+
+    Some((result, flags))
+}
+
+/// The operations needed to round results in any mode, for both formats.
+trait Float:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const MAX: Self;
+    const MIN_POSITIVE: Self;
+    const INFINITY: Self;
+
+    fn is_nan(self) -> bool;
+    fn is_finite(self) -> bool;
+    fn is_sign_negative(self) -> bool;
+    /// A NaN with its quiet bit, the top bit of the fraction, clear.
+    fn is_signalling(self) -> bool;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn mul_add(self, a: Self, b: Self) -> Self;
+    fn next_up(self) -> Self;
+    fn next_down(self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($type:ty, $quiet:expr) => {
+        impl Float for $type {
+            const ZERO: Self = 0.0;
+            const MAX: Self = <$type>::MAX;
+            const MIN_POSITIVE: Self = <$type>::MIN_POSITIVE;
+            const INFINITY: Self = <$type>::INFINITY;
+
+            fn is_nan(self) -> bool {
+                <$type>::is_nan(self)
+            }
+            fn is_finite(self) -> bool {
+                <$type>::is_finite(self)
+            }
+            fn is_sign_negative(self) -> bool {
+                <$type>::is_sign_negative(self)
+            }
+            fn is_signalling(self) -> bool {
+                <$type>::is_nan(self) && self.to_bits() & $quiet == 0
+            }
+            fn abs(self) -> Self {
+                <$type>::abs(self)
+            }
+            fn sqrt(self) -> Self {
+                <$type>::sqrt(self)
+            }
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                <$type>::mul_add(self, a, b)
+            }
+            fn next_up(self) -> Self {
+                <$type>::next_up(self)
+            }
+            fn next_down(self) -> Self {
+                <$type>::next_down(self)
+            }
+        }
+    };
+}
+
+impl_float!(f32, 1 << 22);
+impl_float!(f64, 1 << 51);
+
+/// Perform add, sub, mul, div or sqrt in the given rounding mode.
+///
+/// The hardware rounds to nearest, so the sign of the rounding error is
+/// recovered exactly with error-free transformations and the result is
+/// then nudged by one unit in the last place where the mode requires.
+fn operate<F: Float>(funct: u32, fs: F, ft: F, mode: RoundingMode) -> (F, u32) {
+    // sqrt has no ft operand; the field is zero and names $f0.
+    let ft_used = funct != 0b000100;
+    let any_nan = fs.is_nan() || (ft_used && ft.is_nan());
+    let mut finite = fs.is_finite() && (!ft_used || ft.is_finite());
+    let mut flags = signalling(fs) | if ft_used { signalling(ft) } else { 0 };
+
+    let (nearest, error) = match funct {
+        0b000000 => two_sum(fs, ft),
+        0b000001 => two_sum(fs, -ft),
+        0b000010 => {
+            let product = fs * ft;
+            (product, sign(fs.mul_add(ft, -product)))
+        }
+        0b000011 => {
+            let quotient = fs / ft;
+            if ft == F::ZERO && fs.is_finite() && fs != F::ZERO {
+                // An exact infinity, not an overflow.
+                flags |= DIVIDE_BY_ZERO;
+                finite = false;
+            }
+            // The remainder fs - quotient * ft has the sign of the error
+            // times the sign of ft.
+            let error = match quotient.is_finite() && ft != F::ZERO {
+                true => sign((-quotient).mul_add(ft, fs)),
+                false => Ordering::Equal,
+            };
+            match ft < F::ZERO {
+                true => (quotient, error.reverse()),
+                false => (quotient, error),
+            }
+        }
+        _ => {
+            let root = fs.sqrt();
+            (root, sign((-root).mul_add(root, fs)))
+        }
+    };
+
+    let (result, round_flags) = round(nearest, error, mode, any_nan, finite);
+
+    // An exact zero sum is +0 unless rounding toward negative, where it is
+    // -0 for anything but two +0 operands.
+    if funct <= 0b000001 && result == F::ZERO && mode == RoundingMode::TowardNegative {
+        let addend = if funct == 0b000001 { -ft } else { ft };
+        if fs.is_sign_negative() || addend.is_sign_negative() || fs != F::ZERO {
+            return (-F::ZERO, flags | round_flags);
+        }
+    }
+
+    (result, flags | round_flags)
+}
+
+/// Add two values, returning the sum rounded to nearest and the sign of
+/// the exact sum minus the rounded sum.
+fn two_sum<F: Float>(a: F, b: F) -> (F, Ordering) {
+    let sum = a + b;
+    if !sum.is_finite() {
+        return (sum, Ordering::Equal);
+    }
+
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    let error = (a - a_virtual) + (b - b_virtual);
+    (sum, sign(error))
+}
+
+// Invalid, if the value is a signalling NaN.
+fn signalling<F: Float>(value: F) -> u32 {
+    if value.is_signalling() {
+        INVALID
+    } else {
+        0
+    }
+}
+
+fn sign<F: Float>(value: F) -> Ordering {
+    value.partial_cmp(&F::ZERO).unwrap_or(Ordering::Equal)
+}
+
+/// Round a result from round-to-nearest into the given mode.
+///
+/// `error` is how the exact result compares to `nearest`. `any_nan` and
+/// `finite` describe the operands, to tell invalid operations and
+/// overflows apart from propagated NaNs and infinities.
+fn round<F: Float>(
+    nearest: F,
+    error: Ordering,
+    mode: RoundingMode,
+    any_nan: bool,
+    finite: bool,
+) -> (F, u32) {
+    if nearest.is_nan() {
+        return (nearest, if any_nan { 0 } else { INVALID });
+    }
+
+    let mut flags = 0;
+    let negative = nearest < F::ZERO;
+
+    let result = if !nearest.is_finite() && finite {
+        // Overflow rounds to infinity or the largest finite value.
+        flags |= OVERFLOW | INEXACT;
+        let to_infinity = match mode {
+            RoundingMode::Nearest => true,
+            RoundingMode::TowardZero => false,
+            RoundingMode::TowardPositive => !negative,
+            RoundingMode::TowardNegative => negative,
+        };
+        match (to_infinity, negative) {
+            (true, false) => F::INFINITY,
+            (true, true) => -F::INFINITY,
+            (false, false) => F::MAX,
+            (false, true) => -F::MAX,
+        }
+    } else if error != Ordering::Equal {
+        flags |= INEXACT;
+        let result = match (mode, error) {
+            (RoundingMode::TowardPositive, Ordering::Greater) => nearest.next_up(),
+            (RoundingMode::TowardNegative, Ordering::Less) => nearest.next_down(),
+            (RoundingMode::TowardZero, Ordering::Less) if nearest > F::ZERO => nearest.next_down(),
+            (RoundingMode::TowardZero, Ordering::Greater) if nearest < F::ZERO => nearest.next_up(),
+            _ => nearest,
+        };
+        if !result.is_finite() {
+            flags |= OVERFLOW;
+        }
+        if result.abs() < F::MIN_POSITIVE {
+            flags |= UNDERFLOW;
+        }
+        result
+    } else {
+        nearest
+    };
+
+    (result, flags)
+}
+
+/// Convert to a 32-bit integer, rounding in the given mode.
+fn to_word(value: f64, mode: RoundingMode) -> (u32, u32) {
+    let rounded = match mode {
+        RoundingMode::Nearest => value.round_ties_even(),
+        RoundingMode::TowardZero => value.trunc(),
+        RoundingMode::TowardPositive => value.ceil(),
+        RoundingMode::TowardNegative => value.floor(),
+    };
+
+    if value.is_nan() || rounded < i32::MIN as f64 || rounded > i32::MAX as f64 {
+        return (INVALID_WORD, INVALID);
+    }
+
+    let flags = if rounded != value { INEXACT } else { 0 };
+    (rounded as i32 as u32, flags)
+}
@@ -1,59 +1,365 @@
-// const BYTES_4K: usize = 4 * 1024;
-const CAPACITY: usize = 16;
+use super::{
+    device::{Device, Ram},
+    error::ErrorKind,
+};
 
-pub struct Memory {
-    pub memory: Vec<u8>,
+// The default layout follows SPIM and MARS.
+pub const TEXT_BASE: u64 = 0x0040_0000;
+pub const DATA_BASE: u64 = 0x1000_0000;
+pub const STATIC_DATA_BASE: u64 = 0x1001_0000;
+pub const GLOBAL_POINTER: u64 = 0x1000_8000;
+pub const HEAP_BASE: u64 = 0x1004_0000;
+pub const STACK_POINTER: u64 = 0x7FFF_FFFC;
+pub const KERNEL_TEXT_BASE: u64 = 0x8000_0000;
+pub const KERNEL_DATA_BASE: u64 = 0x9000_0000;
+pub const MMIO_BASE: u64 = 0xFFFF_0000;
+
+/// How many bits of an address select a location in memory.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum AddressWidth {
+    /// Only the low 32 bits of an address are used, so sign-extended
+    /// addresses such as 0xFFFF_FFFF_8000_0000 refer to 0x8000_0000.
+    #[default]
+    Bits32,
+    Bits64,
 }
 
-impl Default for Memory {
+/// The order in which the bytes of a halfword, word or doubleword are
+/// stored, for data and instructions alike.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Endianness {
+    /// The most significant byte is at the lowest address, as on the
+    /// R2000/R3000 and in SPIM and MARS by default.
+    #[default]
+    Big,
+    /// The least significant byte is at the lowest address (mipsel).
+    Little,
+}
+
+/// Size and layout options for a [`Memory`].
+///
+/// Each segment runs from its base up to the base of the next one. The
+/// stack is the exception: it grows down from the initial stack pointer
+/// toward the end of the heap.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryConfig {
+    pub address_width: AddressWidth,
+    pub endianness: Endianness,
+    /// Addresses at or above this limit are address errors, unless a device
+    /// claims them. When unset, the whole address space is usable.
+    pub size: Option<u64>,
+    /// Where programs are loaded, and the initial PC.
+    pub text_base: u64,
+    pub data_base: u64,
+    /// Where the `.data` directive places data, above the `.extern` area.
+    pub static_data_base: u64,
+    /// The initial value of `$gp`.
+    pub global_pointer: u64,
+    /// Where `sbrk` starts allocating.
+    pub heap_base: u64,
+    /// The initial value of `$sp`.
+    pub stack_pointer: u64,
+    pub kernel_text_base: u64,
+    pub kernel_data_base: u64,
+    pub mmio_base: u64,
+}
+
+impl Default for MemoryConfig {
     fn default() -> Self {
         Self {
-            memory: vec![0; CAPACITY],
+            address_width: AddressWidth::Bits32,
+            endianness: Endianness::Big,
+            size: None,
+            text_base: TEXT_BASE,
+            data_base: DATA_BASE,
+            static_data_base: STATIC_DATA_BASE,
+            global_pointer: GLOBAL_POINTER,
+            heap_base: HEAP_BASE,
+            stack_pointer: STACK_POINTER,
+            kernel_text_base: KERNEL_TEXT_BASE,
+            kernel_data_base: KERNEL_DATA_BASE,
+            mmio_base: MMIO_BASE,
         }
     }
 }
 
-impl std::fmt::Debug for Memory {
+/// The purpose of a region of memory.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SegmentKind {
+    Text,
+    Data,
+    Heap,
+    Stack,
+    KernelText,
+    KernelData,
+    Mmio,
+}
+
+impl std::fmt::Display for SegmentKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (i, value) in self.memory.iter().enumerate() {
-            if i % 4 == 0 {
-                write!(f, "{:04}   ", i)?;
-            }
-            write!(f, "{:08b}", value)?;
-            if i % 4 < 3 {
-                write!(f, " ")?;
-            } else if i % 4 == 3 && i > 0 && i < self.memory.len() - 1 {
-                writeln!(f)?;
-            }
+        match self {
+            SegmentKind::Text => write!(f, "text"),
+            SegmentKind::Data => write!(f, "data"),
+            SegmentKind::Heap => write!(f, "heap"),
+            SegmentKind::Stack => write!(f, "stack"),
+            SegmentKind::KernelText => write!(f, "kernel text"),
+            SegmentKind::KernelData => write!(f, "kernel data"),
+            SegmentKind::Mmio => write!(f, "MMIO"),
         }
+    }
+}
+
+/// A named region of memory, from `start` up to but not including `end`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Segment {
+    pub fn contains(&self, address: u64) -> bool {
+        self.start <= address && address < self.end
+    }
+}
+
+/// The memory bus. Devices claim address ranges, and everything they do
+/// not claim is served by sparse RAM.
+pub struct Memory {
+    config: MemoryConfig,
+    ram: Ram,
+    devices: Vec<Mapping>,
+    heap_end: u64,
+}
+
+// A device and the range of addresses it claims, from start up to but not
+// including end.
+struct Mapping {
+    start: u64,
+    end: u64,
+    device: Box<dyn Device>,
+}
 
-        Ok(())
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new(MemoryConfig::default())
+    }
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.ram.fmt(f)
     }
 }
 
 impl Memory {
-    // Assume a proper address for now.
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            config,
+            ram: Ram::new(config.endianness, config.size),
+            devices: Vec::new(),
+            heap_end: config.heap_base,
+        }
+    }
+
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// Attach a device to the bus, claiming addresses from `start` up to
+    /// but not including `end`. Devices attached later take priority where
+    /// ranges overlap.
+    pub fn attach(&mut self, start: u64, end: u64, device: Box<dyn Device>) {
+        self.devices.push(Mapping { start, end, device });
+    }
+
+    /// All segments, in address order.
+    pub fn segments(&self) -> [Segment; 7] {
+        let config = &self.config;
+        let segment = |kind, start, end| Segment { kind, start, end };
+
+        [
+            segment(SegmentKind::Text, config.text_base, config.data_base),
+            segment(SegmentKind::Data, config.data_base, config.heap_base),
+            segment(SegmentKind::Heap, config.heap_base, self.heap_end),
+            segment(SegmentKind::Stack, self.heap_end, config.kernel_text_base),
+            segment(
+                SegmentKind::KernelText,
+                config.kernel_text_base,
+                config.kernel_data_base,
+            ),
+            segment(
+                SegmentKind::KernelData,
+                config.kernel_data_base,
+                config.mmio_base,
+            ),
+            segment(SegmentKind::Mmio, config.mmio_base, 1 << 32),
+        ]
+    }
+
+    /// The segment containing an address, if any.
+    pub fn segment_at(&self, address: u64) -> Option<Segment> {
+        let address = match self.config.address_width {
+            AddressWidth::Bits32 => address as u32 as u64,
+            AddressWidth::Bits64 => address,
+        };
+
+        self.segments()
+            .into_iter()
+            .find(|segment| segment.contains(address))
+    }
+
+    /// Grow the heap by `size` bytes and return the start of the new
+    /// space, as `sbrk` does. The heap stays word-aligned.
+    ///
+    /// Returns `None`, leaving the heap as it was, if it would grow past
+    /// the stack pointer or into the kernel.
+    pub fn sbrk(&mut self, size: u64, stack_pointer: u64) -> Option<u64> {
+        let stack_pointer = match self.config.address_width {
+            AddressWidth::Bits32 => stack_pointer as u32 as u64,
+            AddressWidth::Bits64 => stack_pointer,
+        };
+        let limit = stack_pointer.min(self.config.kernel_text_base);
+
+        let address = self.heap_end;
+        let end = address.checked_add(size)?.checked_add(3)? & !3;
+        if end > limit {
+            return None;
+        }
+        self.heap_end = end;
+        Some(address)
+    }
+
+    /// The interrupt lines asserted by any attached device.
+    pub fn pending_interrupts(&self) -> u8 {
+        self.devices
+            .iter()
+            .fold(0, |lines, mapping| lines | mapping.device.interrupts())
+    }
+
+    /// The number of pages of RAM that have been allocated so far.
+    pub fn page_count(&self) -> usize {
+        self.ram.page_count()
+    }
+
+    // A doubleword is 64 bits.
+    pub fn store_doubleword(&mut self, address: u64, data: u64) -> Result<(), ErrorKind> {
+        self.write(address, 8, data)
+    }
+
+    // A doubleword is 64 bits.
+    pub fn load_doubleword(&mut self, address: u64) -> Result<u64, ErrorKind> {
+        self.read(address, 8)
+    }
+
     // A word is 32 bits.
-    pub fn store_word(&mut self, address: u64, data: u32) {
-        println!("Storing {data} at {address}");
-        let address = address as usize;
-        self.memory[address] = ((data >> 24) & 0b11111111) as u8;
-        self.memory[address + 1] = ((data >> 16) & 0b11111111) as u8;
-        self.memory[address + 2] = ((data >> 8) & 0b11111111) as u8;
-        self.memory[address + 3] = (data & 0b11111111) as u8;
+    pub fn store_word(&mut self, address: u64, data: u32) -> Result<(), ErrorKind> {
+        self.write(address, 4, data as u64)
     }
 
-    // Assume a proper address for now.
     // A word is 32 bits.
-    pub fn load_word(&self, address: u64) -> u32 {
-        let address = address as usize;
-        let mut result: u32 = 0;
+    pub fn load_word(&mut self, address: u64) -> Result<u32, ErrorKind> {
+        Ok(self.read(address, 4)? as u32)
+    }
 
-        result |= (self.memory[address] as u32) << 24;
-        result |= (self.memory[address + 1] as u32) << 16;
-        result |= (self.memory[address + 2] as u32) << 8;
-        result |= self.memory[address + 3] as u32;
+    // A halfword is 16 bits.
+    pub fn store_half(&mut self, address: u64, data: u16) -> Result<(), ErrorKind> {
+        self.write(address, 2, data as u64)
+    }
+
+    // A halfword is 16 bits.
+    pub fn load_half(&mut self, address: u64) -> Result<u16, ErrorKind> {
+        Ok(self.read(address, 2)? as u16)
+    }
+
+    pub fn store_byte(&mut self, address: u64, data: u8) -> Result<(), ErrorKind> {
+        self.write(address, 1, data as u64)
+    }
+
+    pub fn load_byte(&mut self, address: u64) -> Result<u8, ErrorKind> {
+        Ok(self.read(address, 1)? as u8)
+    }
+
+    fn write(&mut self, address: u64, size: u64, value: u64) -> Result<(), ErrorKind> {
+        let address_error = |address| ErrorKind::AddressErrorStore { address };
+        let start = self.check_address(address, size, address_error)?;
+        let (device, offset) = self.device_at(start, size, address, address_error)?;
+
+        device
+            .write(offset, size, value)
+            .map_err(|error| with_address(error, address))
+    }
+
+    fn read(&mut self, address: u64, size: u64) -> Result<u64, ErrorKind> {
+        let address_error = |address| ErrorKind::AddressErrorLoad { address };
+        let start = self.check_address(address, size, address_error)?;
+        let (device, offset) = self.device_at(start, size, address, address_error)?;
+
+        device
+            .read(offset, size)
+            .map_err(|error| with_address(error, address))
+    }
+
+    // Find the device that claims an access, and the offset into it.
+    // Accesses that are only partly claimed are bus errors, and unclaimed
+    // accesses past the end of RAM are address errors.
+    fn device_at(
+        &mut self,
+        start: u64,
+        size: u64,
+        address: u64,
+        address_error: fn(u64) -> ErrorKind,
+    ) -> Result<(&mut dyn Device, u64), ErrorKind> {
+        let last = start + (size - 1);
+        let mapping = self
+            .devices
+            .iter_mut()
+            .rev()
+            .find(|mapping| mapping.start <= last && start < mapping.end);
+
+        match mapping {
+            Some(mapping) if mapping.start <= start && last < mapping.end => {
+                Ok((mapping.device.as_mut(), start - mapping.start))
+            }
+            Some(_) => Err(ErrorKind::BusError { address }),
+            None => match self.config.size {
+                Some(limit) if last >= limit => Err(address_error(address)),
+                _ => Ok((&mut self.ram, start)),
+            },
+        }
+    }
+
+    // Misaligned accesses, and addresses outside the address space, are
+    // address errors. In a 32-bit space an address must fit in 32 bits,
+    // either zero- or sign-extended.
+    //
+    // Returns the address with any bits beyond the address width removed.
+    fn check_address(
+        &self,
+        address: u64,
+        size: u64,
+        address_error: fn(u64) -> ErrorKind,
+    ) -> Result<u64, ErrorKind> {
+        if !address.is_multiple_of(size) {
+            return Err(address_error(address));
+        }
+
+        match self.config.address_width {
+            AddressWidth::Bits32 => {
+                if address >> 32 != 0 && address as i32 as u64 != address {
+                    return Err(address_error(address));
+                }
+                Ok(address as u32 as u64)
+            }
+            AddressWidth::Bits64 => Ok(address),
+        }
+    }
+}
 
-        result
+// Devices report bus errors by offset; report them by the full address.
+fn with_address(error: ErrorKind, address: u64) -> ErrorKind {
+    match error {
+        ErrorKind::BusError { .. } => ErrorKind::BusError { address },
+        error => error,
     }
 }
@@ -1,15 +1,27 @@
+use super::memory::MemoryConfig;
 use std::ops::{Index, IndexMut};
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Registers {
     pub pc: u64,
     pub gpr: [u64; 32],
     pub fpr: [u64; 32],
-    pub cc: u64,
+    pub fcsr: u64,
+    pub hi: u64,
+    pub lo: u64,
 }
 
+/// The assembly names of the general-purpose registers, indexed by number.
+pub const GPR_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra",
+];
+
 pub enum RegisterType {
-    Cc = -2,
+    Hi = -4,
+    Lo = -3,
+    Fcsr = -2,
     Pc = -1,
     Zero = 0,
     At = 1,
@@ -77,6 +89,76 @@ pub enum RegisterType {
     F31 = 63,
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new(&MemoryConfig::default())
+    }
+}
+
+impl Registers {
+    /// Registers with `pc`, `$gp` and `$sp` set for the given memory layout.
+    pub fn new(config: &MemoryConfig) -> Self {
+        let mut gpr = [0; 32];
+        gpr[RegisterType::Gp as usize] = config.global_pointer;
+        gpr[RegisterType::Sp as usize] = config.stack_pointer;
+
+        Self {
+            pc: config.text_base,
+            gpr,
+            fpr: [0; 32],
+            fcsr: 0,
+            hi: 0,
+            lo: 0,
+        }
+    }
+
+    /// Get one of the eight floating-point condition codes in the FCSR.
+    pub fn get_condition_code(&self, cc: u32) -> bool {
+        self.fcsr & condition_code_mask(cc) != 0
+    }
+
+    /// Set one of the eight floating-point condition codes in the FCSR.
+    pub fn set_condition_code(&mut self, cc: u32, value: bool) {
+        if value {
+            self.fcsr |= condition_code_mask(cc);
+        } else {
+            self.fcsr &= !condition_code_mask(cc);
+        }
+    }
+
+    /// Read a floating-point control register by number, as `cfc1` does.
+    pub fn get_control_register(&self, register: u32) -> Option<u64> {
+        match register {
+            0 => Some(FIR),
+            31 => Some(self.fcsr),
+            _ => None,
+        }
+    }
+
+    /// Write a floating-point control register by number, as `ctc1` does.
+    ///
+    /// The FIR is read-only and, like unimplemented registers, ignores writes.
+    pub fn set_control_register(&mut self, register: u32, value: u64) {
+        if register == 31 {
+            self.fcsr = value & FCSR_WRITE_MASK;
+        }
+    }
+}
+
+// The FIR reports single, double and word formats with 64-bit registers.
+const FIR: u64 = (1 << 22) | (1 << 20) | (1 << 17) | (1 << 16);
+
+// FCSR bits 18-22 are reserved and always read as zero.
+const FCSR_WRITE_MASK: u64 = 0xFF83_FFFF;
+
+// Condition code 0 is FCSR bit 23, while condition codes 1-7 are bits 25-31.
+fn condition_code_mask(cc: u32) -> u64 {
+    match cc {
+        0 => 1 << 23,
+        _ => 1 << (24 + cc),
+    }
+}
+
 impl Index<&str> for Registers {
     type Output = u64;
 
@@ -115,7 +197,9 @@ impl Index<&str> for Registers {
             "sp" => &self.gpr[29],
             "fp" => &self.gpr[30],
             "ra" => &self.gpr[31],
-            "cc" => &self.cc,
+            "fcsr" => &self.fcsr,
+            "hi" => &self.hi,
+            "lo" => &self.lo,
             "f0" => &self.fpr[0],
             "f1" => &self.fpr[1],
             "f2" => &self.fpr[2],
@@ -191,7 +275,9 @@ impl Index<RegisterType> for Registers {
             RegisterType::Sp => &self.gpr[29],
             RegisterType::Fp => &self.gpr[30],
             RegisterType::Ra => &self.gpr[31],
-            RegisterType::Cc => &self.cc,
+            RegisterType::Fcsr => &self.fcsr,
+            RegisterType::Hi => &self.hi,
+            RegisterType::Lo => &self.lo,
             RegisterType::F0 => &self.fpr[0],
             RegisterType::F1 => &self.fpr[1],
             RegisterType::F2 => &self.fpr[2],
@@ -264,7 +350,9 @@ impl IndexMut<&str> for Registers {
             "sp" => &mut self.gpr[29],
             "fp" => &mut self.gpr[30],
             "ra" => &mut self.gpr[31],
-            "cc" => &mut self.cc,
+            "fcsr" => &mut self.fcsr,
+            "hi" => &mut self.hi,
+            "lo" => &mut self.lo,
             "f0" => &mut self.fpr[0],
             "f1" => &mut self.fpr[1],
             "f2" => &mut self.fpr[2],
@@ -338,7 +426,9 @@ impl IndexMut<RegisterType> for Registers {
             RegisterType::Sp => &mut self.gpr[29],
             RegisterType::Fp => &mut self.gpr[30],
             RegisterType::Ra => &mut self.gpr[31],
-            RegisterType::Cc => &mut self.cc,
+            RegisterType::Fcsr => &mut self.fcsr,
+            RegisterType::Hi => &mut self.hi,
+            RegisterType::Lo => &mut self.lo,
             RegisterType::F0 => &mut self.fpr[0],
             RegisterType::F1 => &mut self.fpr[1],
             RegisterType::F2 => &mut self.fpr[2],
@@ -0,0 +1,330 @@
+use super::{
+    error::ErrorKind,
+    memory::{AddressWidth, Memory},
+    registers::Registers,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+/// Reads and writes move at most this many bytes through the host at a
+/// time, however long the buffer the program gives.
+const CHUNK_SIZE: usize = 4096;
+
+/// How the datapath treats the `syscall` instruction.
+#[derive(Default, Copy, Clone, Eq, PartialEq)]
+pub enum SyscallMode {
+    /// Service SPIM/MARS-style system calls directly.
+    #[default]
+    Emulate,
+    /// Raise a Syscall exception for a handler (or the caller) to deal with.
+    Exception,
+}
+
+/// SPIM/MARS-compatible system call services, selected by `$v0`.
+///
+/// The standard streams are pluggable so that output can be captured and
+/// input scripted, for example with [`SharedBuffer`] and [`io::Cursor`].
+pub struct SyscallHandler {
+    pub stdin: Box<dyn BufRead>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+
+    files: HashMap<u32, File>,
+    next_descriptor: u32,
+}
+
+impl Default for SyscallHandler {
+    fn default() -> Self {
+        Self {
+            stdin: Box::new(io::BufReader::new(io::stdin())),
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            files: HashMap::new(),
+            // 0, 1 and 2 are the standard streams.
+            next_descriptor: 3,
+        }
+    }
+}
+
+impl SyscallHandler {
+    /// Service the system call requested in `$v0`.
+    ///
+    /// Returns the exit code if the program asked to exit.
+    pub fn handle(
+        &mut self,
+        registers: &mut Registers,
+        memory: &mut Memory,
+    ) -> Result<Option<i32>, ErrorKind> {
+        let a0 = registers.gpr[4];
+        let a1 = registers.gpr[5];
+        let a2 = registers.gpr[6];
+
+        let v0 = match registers.gpr[2] {
+            // print_int
+            1 => {
+                self.print(format!("{}", a0 as i32).as_bytes());
+                return Ok(None);
+            }
+            // print_string
+            4 => {
+                let string = read_string(memory, a0)?;
+                self.print(&string);
+                return Ok(None);
+            }
+            // read_int
+            5 => {
+                let line = self.read_line();
+                line.trim().parse::<i32>().unwrap_or(0) as u64
+            }
+            // read_string
+            8 => {
+                self.read_string(memory, a0, a1 as i32)?;
+                return Ok(None);
+            }
+            // sbrk, which returns -1 when out of memory, as in MARS
+            9 => memory
+                .sbrk((a0 as i32).max(0) as u64, registers.gpr[29])
+                .unwrap_or(-1i64 as u64),
+            // exit
+            10 => return Ok(Some(0)),
+            // print_char
+            11 => {
+                self.print(&[a0 as u8]);
+                return Ok(None);
+            }
+            // read_char
+            12 => {
+                let mut byte = [0];
+                match self.stdin.read(&mut byte) {
+                    Ok(1) => byte[0] as u64,
+                    _ => 0,
+                }
+            }
+            // open
+            13 => self.open(memory, a0, a1)? as i64 as u64,
+            // read
+            14 => self.read(memory, a0 as u32, a1, a2 as i32)? as i64 as u64,
+            // write
+            15 => self.write(memory, a0 as u32, a1, a2 as i32)? as i64 as u64,
+            // close
+            16 => {
+                self.files.remove(&(a0 as u32));
+                return Ok(None);
+            }
+            // exit2
+            17 => return Ok(Some(a0 as i32)),
+            code => return Err(ErrorKind::UnsupportedSyscall(code)),
+        };
+
+        registers.gpr[2] = v0;
+        Ok(None)
+    }
+
+    fn print(&mut self, bytes: &[u8]) {
+        // Output errors on the host are not the emulated program's concern.
+        let _ = self.stdout.write_all(bytes);
+        let _ = self.stdout.flush();
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        let _ = self.stdin.read_line(&mut line);
+        line
+    }
+
+    // Like SPIM, read at most length - 1 characters, keep the newline,
+    // and terminate the string with a null byte.
+    fn read_string(
+        &mut self,
+        memory: &mut Memory,
+        address: u64,
+        length: i32,
+    ) -> Result<(), ErrorKind> {
+        if length < 1 {
+            return Ok(());
+        }
+
+        let line = self.read_line();
+        let bytes = line.as_bytes();
+        let count = bytes.len().min(length as usize - 1);
+
+        for (i, byte) in bytes[..count].iter().enumerate() {
+            memory.store_byte(address.wrapping_add(i as u64), *byte)?;
+        }
+        memory.store_byte(address.wrapping_add(count as u64), 0)
+    }
+
+    // Flags are 0 for reading, 1 for writing and 9 for appending.
+    fn open(&mut self, memory: &mut Memory, name: u64, flags: u64) -> Result<i32, ErrorKind> {
+        let name = read_string(memory, name)?;
+        let name = String::from_utf8_lossy(&name).into_owned();
+
+        let file = match flags {
+            0 => File::open(name),
+            1 => File::create(name),
+            9 => OpenOptions::new().append(true).create(true).open(name),
+            _ => return Ok(-1),
+        };
+
+        match file {
+            Ok(file) => {
+                let descriptor = self.next_descriptor;
+                self.next_descriptor += 1;
+                self.files.insert(descriptor, file);
+                Ok(descriptor as i32)
+            }
+            Err(_) => Ok(-1),
+        }
+    }
+
+    fn read(
+        &mut self,
+        memory: &mut Memory,
+        descriptor: u32,
+        address: u64,
+        length: i32,
+    ) -> Result<i32, ErrorKind> {
+        let Some(length) = transfer_length(memory, address, length) else {
+            return Ok(-1);
+        };
+        if descriptor != 0 && !self.files.contains_key(&descriptor) {
+            return Ok(-1);
+        }
+        // Fault on a bad buffer before consuming any input, as the store
+        // of its first byte would.
+        if length > 0 {
+            let byte = memory.load_byte(address).map_err(|error| match error {
+                ErrorKind::AddressErrorLoad { address } => ErrorKind::AddressErrorStore { address },
+                error => error,
+            })?;
+            memory.store_byte(address, byte)?;
+        }
+
+        let mut buffer = [0; CHUNK_SIZE];
+        let mut total = 0;
+        while total < length {
+            let wanted = (length - total).min(CHUNK_SIZE);
+            let count = match descriptor {
+                0 => self.stdin.read(&mut buffer[..wanted]),
+                _ => match self.files.get_mut(&descriptor) {
+                    Some(file) => file.read(&mut buffer[..wanted]),
+                    None => return Ok(-1),
+                },
+            };
+            let Ok(count) = count else {
+                return Ok(-1);
+            };
+
+            for (i, byte) in buffer[..count].iter().enumerate() {
+                memory.store_byte(address.wrapping_add((total + i) as u64), *byte)?;
+            }
+            total += count;
+            // A short read means nothing more is available yet.
+            if count < wanted {
+                break;
+            }
+        }
+        Ok(total as i32)
+    }
+
+    fn write(
+        &mut self,
+        memory: &mut Memory,
+        descriptor: u32,
+        address: u64,
+        length: i32,
+    ) -> Result<i32, ErrorKind> {
+        let Some(length) = transfer_length(memory, address, length) else {
+            return Ok(-1);
+        };
+        if !matches!(descriptor, 1 | 2) && !self.files.contains_key(&descriptor) {
+            return Ok(-1);
+        }
+
+        let mut buffer = Vec::with_capacity(length.min(CHUNK_SIZE));
+        let mut total = 0;
+        while total < length {
+            buffer.clear();
+            for i in total..length.min(total + CHUNK_SIZE) {
+                buffer.push(memory.load_byte(address.wrapping_add(i as u64))?);
+            }
+
+            let result = match descriptor {
+                1 => self
+                    .stdout
+                    .write_all(&buffer)
+                    .and_then(|_| self.stdout.flush()),
+                2 => self
+                    .stderr
+                    .write_all(&buffer)
+                    .and_then(|_| self.stderr.flush()),
+                _ => match self.files.get_mut(&descriptor) {
+                    Some(file) => file.write_all(&buffer),
+                    None => return Ok(-1),
+                },
+            };
+            if result.is_err() {
+                return Ok(-1);
+            }
+            total += buffer.len();
+        }
+        Ok(total as i32)
+    }
+}
+
+/// How many bytes a read or write of `length` bytes at `address` may
+/// transfer: none if the length is negative, as in SPIM and MARS, and never
+/// past the end of the segment holding the address.
+fn transfer_length(memory: &Memory, address: u64, length: i32) -> Option<usize> {
+    let length = usize::try_from(length).ok()?;
+    let address = match memory.config().address_width {
+        AddressWidth::Bits32 => address as u32 as u64,
+        AddressWidth::Bits64 => address,
+    };
+
+    // An address outside every segment faults on its first access.
+    let remaining = memory
+        .segment_at(address)
+        .map_or(length as u64, |segment| segment.end - address);
+    Some(length.min(remaining.min(usize::MAX as u64) as usize))
+}
+
+/// Read a null-terminated string from memory.
+fn read_string(memory: &mut Memory, address: u64) -> Result<Vec<u8>, ErrorKind> {
+    let mut string = Vec::new();
+
+    for i in 0.. {
+        match memory.load_byte(address.wrapping_add(i))? {
+            0 => break,
+            byte => string.push(byte),
+        }
+    }
+
+    Ok(string)
+}
+
+/// An in-memory stream that can be handed to the datapath while still
+/// being readable by its creator.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Everything written so far, as text.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,2 +1,8 @@
 #[cfg(test)]
+pub mod assembler;
+#[cfg(test)]
+pub mod disassembler;
+#[cfg(test)]
+pub mod memory;
+#[cfg(test)]
 pub mod mips_datapath;
@@ -0,0 +1,598 @@
+use crate::datapath::Datapath;
+use crate::mips::assembler::{
+    assemble, assemble_file, AssemblyError, AssemblyErrorKind, SourceLine,
+};
+use crate::mips::datapath::MipsDatapath;
+use crate::mips::memory::MemoryConfig;
+use crate::mips::registers::RegisterType;
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn encodes_instructions() {
+    let source = "
+        add $t1, $t1, $t0       # register names
+        addi $9, $8, -1         # register numbers
+        sll $zero, $zero, 0
+        lw $t1, 4($t0)
+        sdc1 $f4, 0x208($zero)
+        bne $t0, $zero, -3
+        jal 0x0040000c
+        mfc0 $t3, $14
+        c.eq.d 3, $f2, $f4
+        cvt.w.s $f2, $f0
+        movt.d $f2, $f0, 0
+    ";
+
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+
+    assert_eq!(
+        program.text,
+        [
+            0b000000_01001_01000_01001_00000_100000,
+            0b001000_01000_01001_1111111111111111,
+            0b000000_00000_00000_00000_00000_000000,
+            0b100011_01000_01001_0000000000000100,
+            0b111101_00000_00100_0000001000001000,
+            0b000101_01000_00000_1111111111111101,
+            0b000011_00000100000000000000000011,
+            0b010000_00000_01011_01110_00000000000,
+            0b010001_10001_00100_00010_01100_110010,
+            0b010001_10000_00000_00000_00010_100100,
+            0b010001_10001_00001_00000_00010_010001,
+        ]
+    );
+}
+
+#[test]
+fn assembled_program_runs() {
+    let source = "
+        addi $t0, $zero, 3
+        add $t1, $t1, $t0
+        addi $t0, $t0, -1
+        bne $t0, $zero, -3
+    ";
+    let mut datapath = MipsDatapath::default();
+
+    let program = assemble(source, datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
+    for _ in 0..10 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::T1], 6);
+    assert_eq!(datapath.registers.pc, program.text_base + 16);
+}
+
+#[test]
+fn reports_errors_with_location() {
+    let config = MemoryConfig::default();
+    let error = |source| assemble(source, &config).unwrap_err().errors[0].clone();
+
+    assert_eq!(
+        error("nop\n  frob $t0"),
+        AssemblyError {
+            kind: AssemblyErrorKind::UnknownMnemonic("frob".to_string()),
+            file: None,
+            line: 2,
+            column: 3,
+            length: 4,
+        }
+    );
+    assert_eq!(
+        error("add $t0, $t1").kind,
+        AssemblyErrorKind::WrongOperandCount {
+            expected: 3,
+            found: 2
+        }
+    );
+    assert_eq!(
+        error("sll $t0, $t1, 32").kind,
+        AssemblyErrorKind::ImmediateOutOfRange {
+            value: 32,
+            min: 0,
+            max: 31
+        }
+    );
+    assert_eq!(
+        error("lw $t0, 0($t10)").to_string(),
+        "1:9: unknown register '$t10'"
+    );
+}
+
+#[test]
+fn lays_out_data_and_labels() {
+    let source = r#"
+        .data
+        .globl message
+message: .asciiz "hi\n"
+values:  .half 1, -2
+        .byte 0xff
+numbers: .word 0x01020304, values
+        .align 3
+pi:     .double 3.5
+        .float 1
+        .space 2
+end:
+        .text
+main:   j main
+    "#;
+
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+
+    let data_base = program.data_base;
+    assert_eq!(program.symbols["message"], data_base);
+    assert_eq!(program.symbols["values"], data_base + 4);
+    assert_eq!(program.symbols["numbers"], data_base + 12);
+    assert_eq!(program.symbols["pi"], data_base + 24);
+    assert_eq!(program.symbols["end"], data_base + 38);
+    assert_eq!(program.symbols["main"], program.text_base);
+    assert_eq!(program.globals, ["message"]);
+    assert_eq!(
+        program.data,
+        [
+            b'h', b'i', b'\n', 0, // message
+            0x00, 0x01, 0xff, 0xfe, 0xff, 0, 0, 0, // values
+            0x01, 0x02, 0x03, 0x04, 0x10, 0x01, 0x00, 0x04, // numbers
+            0, 0, 0, 0, // .align 3
+            0x40, 0x0c, 0, 0, 0, 0, 0, 0, // pi
+            0x3f, 0x80, 0, 0, // .float 1
+            0, 0, // .space 2
+        ]
+    );
+}
+
+#[test]
+fn branches_to_labels() {
+    let source = "
+        .data
+count:  .word 3
+        .text
+        lui $t2, 0x1001
+        lw $t0, 0($t2)
+loop:   add $t1, $t1, $t0
+        addi $t0, $t0, -1
+        bne $t0, $zero, loop
+        j done
+        nop
+done:   sw $t1, 4($t2)
+    ";
+    let mut datapath = MipsDatapath::default();
+
+    let program = assemble(source, datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
+    for _ in 0..13 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(program.text[4] & 0xFFFF, 0xFFFD);
+    assert_eq!(datapath.registers[RegisterType::T1], 6);
+    assert_eq!(datapath.memory.load_word(program.data_base + 4).unwrap(), 6);
+}
+
+#[test]
+fn reports_label_errors() {
+    let config = MemoryConfig::default();
+    let error = |source| assemble(source, &config).unwrap_err().errors[0].clone();
+
+    assert_eq!(
+        error("loop: nop\nloop: nop").to_string(),
+        "2:1: label 'loop' is already defined"
+    );
+    assert_eq!(
+        error("nop\nbeq $t0, $t1, nowhere").to_string(),
+        "2:15: undefined label 'nowhere'"
+    );
+    assert_eq!(
+        error(".word 1").kind,
+        AssemblyErrorKind::DataOutsideData(".word".to_string())
+    );
+    assert_eq!(
+        error(".data\nadd $t0, $t0, $t0").kind,
+        AssemblyErrorKind::InstructionOutsideText("add".to_string())
+    );
+}
+
+#[test]
+fn space_with_inverted_layout_is_an_error() {
+    let config = MemoryConfig {
+        heap_base: 0x1000_0000,
+        static_data_base: 0x1001_0000,
+        ..Default::default()
+    };
+
+    let diagnostics = assemble(".data\n.space 4", &config).unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors[0].kind,
+        AssemblyErrorKind::InvalidMemoryLayout
+    );
+    assert_eq!(
+        (diagnostics.errors[0].line, diagnostics.errors[0].column),
+        (2, 1)
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn expands_pseudo_instructions() {
+    let source = "
+        .data
+value:  .word 7
+        .text
+        li $t0, 0x12345678
+        la $t1, value
+        move $t2, $t0
+        blt $t0, $t1, 2
+        addi $t3, $t3, 0x10000
+    ";
+
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+
+    assert_eq!(
+        program.text,
+        [
+            0b001111_00000_01000_0001001000110100,   // lui $t0, 0x1234
+            0b001101_01000_01000_0101011001111000,   // ori $t0, $t0, 0x5678
+            0b001111_00000_01001_0001000000000001,   // lui $t1, 0x1001
+            0b001101_01001_01001_0000000000000000,   // ori $t1, $t1, 0
+            0b000000_00000_01000_01010_00000_100001, // addu $t2, $zero, $t0
+            0b000000_01000_01001_00001_00000_101010, // slt $at, $t0, $t1
+            0b000101_00001_00000_0000000000000010,   // bne $at, $zero, 2
+            0b001111_00000_00001_0000000000000001,   // lui $at, 1
+            0b000000_01011_00001_01011_00000_100000, // add $t3, $t3, $at
+        ]
+    );
+    let lines: Vec<usize> = program.lines.iter().map(|line| line.line).collect();
+    assert_eq!(lines, [5, 5, 6, 6, 7, 8, 8, 9, 9]);
+    assert_eq!(program.line_at(program.text_base + 12).unwrap().line, 6);
+    assert_eq!(
+        program.addresses_of(&SourceLine {
+            file: None,
+            line: 8
+        }),
+        [program.text_base + 20, program.text_base + 24]
+    );
+}
+
+#[test]
+fn pseudo_instructions_run() {
+    let source = "
+        li $t0, -5
+        li $t1, 3
+        neg $t2, $t0
+        not $t3, $zero
+        mul $t4, $t0, $t1
+        li $s0, 0
+loop:   addi $s0, $s0, 1
+        ble $s0, $t1, loop
+        bgt $t0, $t1, done
+        bnez $t0, skip
+        b done
+skip:   li $s1, 1
+done:   nop
+    ";
+    let mut datapath = MipsDatapath::default();
+
+    let program = assemble(source, datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
+    while datapath.registers.pc < program.text_base + program.text.len() as u64 * 4 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::T2], 5);
+    assert_eq!(datapath.registers[RegisterType::T3], u64::MAX);
+    assert_eq!(datapath.registers[RegisterType::T4], -15_i64 as u64);
+    assert_eq!(datapath.registers[RegisterType::S0], 4);
+    assert_eq!(datapath.registers[RegisterType::S1], 1);
+}
+
+#[test]
+fn branches_compare_with_immediates() {
+    let source = "
+        li $s0, 0
+count:  addi $s0, $s0, 1
+        blt $s0, 10, count
+        li $s1, 0
+        bgt $s0, 70000, skip
+        ble $s0, 9, skip
+        bge $s0, -1, done
+skip:   li $s1, 1
+done:   nop
+    ";
+    let mut datapath = MipsDatapath::default();
+
+    let program = assemble(source, datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
+    while datapath.registers.pc < program.text_base + program.text.len() as u64 * 4 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::S0], 10);
+    assert_eq!(datapath.registers[RegisterType::S1], 0);
+    // addiu $at, $zero, 10; slt $at, $s0, $at
+    assert_eq!(program.text[2..4], [0x2401_000A, 0x0201_082A]);
+}
+
+#[test]
+fn refuses_at_in_pseudo_instructions() {
+    let config = MemoryConfig::default();
+    let error = |source| assemble(source, &config).unwrap_err().errors[0].clone();
+
+    assert_eq!(
+        error("blt $at, $t0, 0").to_string(),
+        "1:5: $at is reserved for the assembler in this instruction"
+    );
+    assert_eq!(
+        error("addiu $1, $t0, 70000").kind,
+        AssemblyErrorKind::ReservedRegister
+    );
+    assert!(assemble("addiu $at, $t0, 7\nli $at, 70000", &config).is_ok());
+}
+
+#[test]
+fn expands_macros_and_constants() {
+    let source = "
+        .eqv COUNT 3
+        .eqv TOTAL $s0
+        .macro sum_to (%limit, %result)
+        li $t0, %limit
+again:  add %result, %result, $t0
+        addi $t0, $t0, -1
+        bnez $t0, again
+        .end_macro
+        sum_to(COUNT, TOTAL)
+        sum_to 2, $s1
+    ";
+    let mut datapath = MipsDatapath::default();
+
+    let program = assemble(source, datapath.memory.config()).unwrap();
+    program.load(&mut datapath.memory).unwrap();
+    while datapath.registers.pc < program.text_base + program.text.len() as u64 * 4 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::S0], 6);
+    assert_eq!(datapath.registers[RegisterType::S1], 3);
+    assert_eq!(program.symbols["again_M0"], program.text_base + 4);
+    assert_eq!(program.lines[0].line, 10);
+    assert_eq!(program.lines[4].line, 11);
+}
+
+#[test]
+fn macro_arguments_keep_memory_operands() {
+    let source = "
+        .macro copy (%from, %to)
+        lw $t0, %from
+        sw $t0, %to
+        .end_macro
+        .macro load (%from)
+        lw $t1, %from
+        .end_macro
+        copy ($a0), ($a1)
+        load ($a1)
+        load(0($a1))
+    ";
+
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+
+    assert_eq!(
+        program.text,
+        [
+            0x8C88_0000, // lw $t0, 0($a0)
+            0xACA8_0000, // sw $t0, 0($a1)
+            0x8CA9_0000, // lw $t1, 0($a1)
+            0x8CA9_0000, // lw $t1, 0($a1)
+        ]
+    );
+}
+
+#[test]
+fn constants_do_not_rename_labels() {
+    let source = "
+        .eqv N 4
+N:      addi $t0, $zero, N
+        j N
+    ";
+
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+
+    assert_eq!(program.symbols["N"], program.text_base);
+    assert_eq!(program.text[0], 0x2008_0004);
+}
+
+#[test]
+fn macro_errors_point_into_the_definition() {
+    let config = MemoryConfig::default();
+    let error = |source| assemble(source, &config).unwrap_err().errors[0].clone();
+
+    let source = "
+        .macro load (%register)
+        lw %register, 0($t9)
+        .end_macro
+        load($t0)
+        load($nope)
+    ";
+    assert_eq!(error(source).to_string(), "3:12: unknown register '$nope'");
+    assert_eq!(
+        error(
+            ".macro forever
+forever
+.end_macro
+forever"
+        )
+        .kind,
+        AssemblyErrorKind::RecursionLimit
+    );
+    assert_eq!(
+        error(
+            ".macro open
+nop"
+        )
+        .kind,
+        AssemblyErrorKind::UnterminatedMacro("open".to_string())
+    );
+}
+
+#[test]
+fn includes_files() {
+    let directory = std::env::temp_dir().join(format!("mini-core-include-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("main.asm"),
+        ".include \"lib.asm\"\n        addi $t0, $zero, ANSWER\n",
+    )
+    .unwrap();
+    std::fs::write(directory.join("lib.asm"), ".eqv ANSWER 42\nnop\nfrob\n").unwrap();
+    std::fs::write(directory.join("self.asm"), ".include \"self.asm\"\n").unwrap();
+
+    let error = assemble_file(&directory.join("main.asm"), &MemoryConfig::default())
+        .unwrap_err()
+        .errors[0]
+        .clone();
+    let recursive = assemble_file(&directory.join("self.asm"), &MemoryConfig::default())
+        .unwrap_err()
+        .errors[0]
+        .clone();
+    std::fs::write(directory.join("lib.asm"), ".eqv ANSWER 42\nnop\n").unwrap();
+    let program = assemble_file(&directory.join("main.asm"), &MemoryConfig::default()).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(error.file, Some(directory.join("lib.asm")));
+    assert_eq!((error.line, error.column), (3, 1));
+    assert_eq!(recursive.kind, AssemblyErrorKind::RecursionLimit);
+    assert_eq!(program.text[1], 0x2008_002A);
+    assert_eq!(program.lines[1].file, Some(directory.join("main.asm")));
+}
+
+#[test]
+fn recursion_limit_is_reported_once() {
+    let source = ".macro twice
+twice
+twice
+.end_macro
+twice
+frob $t0
+";
+
+    let diagnostics = assemble(source, &MemoryConfig::default()).unwrap_err();
+
+    let errors: Vec<_> = diagnostics
+        .errors
+        .iter()
+        .map(|error| (error.line, error.kind.clone()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (2, AssemblyErrorKind::RecursionLimit),
+            (6, AssemblyErrorKind::UnknownMnemonic("frob".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn empty_operands_point_at_their_comma() {
+    let source = "add $t0, , $t1
+add $t0, $t1,
+add , $t0, $t1
+";
+
+    let diagnostics = assemble(source, &MemoryConfig::default()).unwrap_err();
+
+    let errors: Vec<_> = diagnostics
+        .errors
+        .iter()
+        .map(|error| (error.line, error.column))
+        .collect();
+    assert_eq!(errors, [(1, 8), (2, 13), (3, 5)]);
+}
+
+#[test]
+fn reports_every_error_in_one_pass() {
+    let source = "
+        .text
+start:  frob $t0
+        add $t0, $t1
+        addi $t0, $t1, 0x100000000
+        beq $t0, $t1, nowhere
+start:  nop
+        j start
+    ";
+
+    let diagnostics = assemble(source, &MemoryConfig::default()).unwrap_err();
+
+    let errors: Vec<_> = diagnostics
+        .errors
+        .iter()
+        .map(|error| (error.line, error.column, error.length, error.kind.clone()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                3,
+                9,
+                4,
+                AssemblyErrorKind::UnknownMnemonic("frob".to_string())
+            ),
+            (
+                4,
+                9,
+                3,
+                AssemblyErrorKind::WrongOperandCount {
+                    expected: 3,
+                    found: 2
+                }
+            ),
+            (
+                5,
+                24,
+                11,
+                AssemblyErrorKind::ImmediateOutOfRange {
+                    value: 0x100000000,
+                    min: i32::MIN as i64,
+                    max: u32::MAX as i64
+                }
+            ),
+            (
+                6,
+                23,
+                7,
+                AssemblyErrorKind::UndefinedLabel("nowhere".to_string())
+            ),
+            (
+                7,
+                1,
+                5,
+                AssemblyErrorKind::DuplicateLabel("start".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn renders_errors_with_excerpts() {
+    let source = format!("\tbeq $t0, $t1, far\n{}far: frob", "nop\n".repeat(0x8000));
+
+    let diagnostics = assemble(&source, &MemoryConfig::default()).unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors[0].kind,
+        AssemblyErrorKind::BranchTargetOutOfRange("far".to_string())
+    );
+    assert_eq!(
+        diagnostics.to_string(),
+        [
+            "1:16: branch target 'far' is too far away",
+            "  |",
+            "1 | \tbeq $t0, $t1, far",
+            "  | \t              ^^^",
+            "32770:6: unknown mnemonic 'frob'",
+            "      |",
+            "32770 | far: frob",
+            "      |      ^^^^",
+            "",
+        ]
+        .join("\n")
+    );
+}
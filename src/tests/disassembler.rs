@@ -0,0 +1,134 @@
+use crate::mips::assembler::assemble;
+use crate::mips::disassembler::{Disassembler, RegisterNames};
+use crate::mips::memory::{Memory, MemoryConfig};
+
+#[test]
+fn disassembles_what_the_assembler_accepts() {
+    let source = "
+        nop
+        add $t1, $t1, $t0
+        nor $v0, $a0, $zero
+        addi $t1, $t0, -1
+        ori $t0, $zero, 0xff
+        lui $at, 0x1001
+        sll $t0, $t1, 4
+        srav $t0, $t1, $t2
+        jalr $t0
+        jalr $s0, $t0
+        mult $a0, $a1
+        lw $t1, 4($t0)
+        sb $t1, -1($sp)
+        sdc1 $f4, 520($zero)
+        mfc0 $k0, $14
+        eret
+        cfc1 $t0, $31
+        add.d $f2, $f4, $f6
+        cvt.w.s $f2, $f0
+        cvt.d.w $f2, $f0
+        c.eq.d 3, $f2, $f4
+        c.lt.s $f0, $f1
+        movt.d $f2, $f0, 1
+        movz.s $f2, $f0, $t0
+        syscall
+    ";
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+    let disassembler = Disassembler::default();
+
+    let lines: Vec<String> = program
+        .text
+        .iter()
+        .map(|word| disassembler.disassemble(*word, 0).unwrap())
+        .collect();
+    let expected: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn resolves_targets_to_addresses_and_symbols() {
+    let source = "
+main:   beq $t0, $zero, done
+        j main
+        bc1t 2, done
+done:   jal 0x00400100
+    ";
+    let program = assemble(source, &MemoryConfig::default()).unwrap();
+    let base = program.text_base;
+
+    let plain = Disassembler::new(RegisterNames::Numeric);
+    let labelled = Disassembler::default().with_symbols(&program.symbols);
+
+    assert_eq!(
+        plain.disassemble(program.text[0], base).unwrap(),
+        "beq $8, $0, 0x0040000c"
+    );
+    assert_eq!(
+        labelled.disassemble(program.text[0], base).unwrap(),
+        "beq $t0, $zero, done"
+    );
+    assert_eq!(
+        labelled.disassemble(program.text[1], base + 4).unwrap(),
+        "j main"
+    );
+    assert_eq!(
+        labelled.disassemble(program.text[2], base + 8).unwrap(),
+        "bc1t 2, done"
+    );
+    assert_eq!(
+        labelled.disassemble(program.text[3], base + 12).unwrap(),
+        "jal 0x00400100"
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn flags_reserved_encodings() {
+    let disassembler = Disassembler::default();
+
+    for word in [
+        0b111111_00000_00000_00000_00000_000000, // unused opcode
+        0b000000_00000_00000_00000_00000_000001, // unused funct
+        0b000001_00000_00010_0000000000000000,   // unused REGIMM rt
+        0b010001_10100_00000_00010_00000_000000, // add.w
+        0b010001_10000_00000_00010_00000_100000, // cvt.s.s
+        0b010001_01000_00011_0000000000000001,   // bc1tl 1
+    ] {
+        assert_eq!(disassembler.disassemble(word, 0), None, "{word:#034b}");
+    }
+}
+
+#[test]
+fn lists_memory() {
+    let source = "
+main:   addi $t0, $zero, 1
+        b main
+    ";
+    let config = MemoryConfig::default();
+    let program = assemble(source, &config).unwrap();
+    let mut memory = Memory::new(config);
+    program.load(&mut memory).unwrap();
+    memory
+        .store_word(program.text_base + 8, 0xFFFF_FFFF)
+        .unwrap();
+
+    let listing = Disassembler::default()
+        .with_symbols(&program.symbols)
+        .listing(&mut memory, program.text_base, 3)
+        .unwrap();
+
+    assert_eq!(
+        listing,
+        [
+            "main:",
+            "0x00400000  20080001  addi $t0, $zero, 1",
+            "0x00400004  1000fffe  beq $zero, $zero, main",
+            "0x00400008  ffffffff  .word 0xffffffff  # reserved",
+            "",
+        ]
+        .join("\n")
+    );
+}
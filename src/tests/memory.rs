@@ -0,0 +1,188 @@
+use crate::mips::device::Device;
+use crate::mips::error::ErrorKind;
+use crate::mips::memory::{
+    AddressWidth, Endianness, Memory, MemoryConfig, SegmentKind, STACK_POINTER, TEXT_BASE,
+};
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[test]
+fn pages_are_allocated_lazily() {
+    let mut memory = Memory::default();
+
+    assert_eq!(memory.load_word(STACK_POINTER).unwrap(), 0);
+    assert_eq!(memory.page_count(), 0);
+
+    memory.store_word(TEXT_BASE, 0x2408_0005).unwrap();
+    memory.store_word(STACK_POINTER, 0xdead_beef).unwrap();
+
+    assert_eq!(memory.page_count(), 2);
+    assert_eq!(memory.load_word(TEXT_BASE).unwrap(), 0x2408_0005);
+    assert_eq!(memory.load_word(STACK_POINTER).unwrap(), 0xdead_beef);
+}
+
+#[test]
+fn sign_extended_addresses_in_32_bit_memory() {
+    let mut memory = Memory::default();
+
+    memory.store_half(0xffff_ffff_8000_0000, 0x1234).unwrap();
+
+    assert_eq!(memory.load_half(0x8000_0000).unwrap(), 0x1234);
+}
+
+#[test]
+fn memory_with_64_bit_addresses() {
+    let mut memory = Memory::new(MemoryConfig {
+        address_width: AddressWidth::Bits64,
+        ..Default::default()
+    });
+
+    memory
+        .store_doubleword(0x0000_0001_0000_0000, 0x0123_4567_89ab_cdef)
+        .unwrap();
+
+    assert_eq!(
+        memory.load_doubleword(0x0000_0001_0000_0000).unwrap(),
+        0x0123_4567_89ab_cdef
+    );
+    assert_eq!(memory.load_doubleword(0).unwrap(), 0);
+}
+
+#[test]
+fn segments_describe_addresses() {
+    let memory = Memory::default();
+    let kind = |address| memory.segment_at(address).unwrap().kind;
+
+    assert_eq!(kind(0x0040_0010), SegmentKind::Text);
+    assert_eq!(kind(0x1001_0000), SegmentKind::Data);
+    assert_eq!(kind(0x7fff_fffc), SegmentKind::Stack);
+    assert_eq!(kind(0xffff_ffff_8000_0180), SegmentKind::KernelText);
+    assert_eq!(kind(0xffff_0008), SegmentKind::Mmio);
+    assert_eq!(memory.segment_at(0), None);
+}
+
+#[test]
+fn address_errors() {
+    let mut memory = Memory::default();
+
+    assert_eq!(
+        memory.store_word(0x1001_0002, 0),
+        Err(ErrorKind::AddressErrorStore {
+            address: 0x1001_0002
+        })
+    );
+    assert_eq!(
+        memory.load_doubleword(0x1001_0004),
+        Err(ErrorKind::AddressErrorLoad {
+            address: 0x1001_0004
+        })
+    );
+    // Neither a zero- nor a sign-extended 32-bit address.
+    assert_eq!(
+        memory.load_byte(0x0000_0001_0000_0000),
+        Err(ErrorKind::AddressErrorLoad {
+            address: 0x0000_0001_0000_0000
+        })
+    );
+}
+
+#[test]
+fn little_endian_byte_order() {
+    let mut memory = Memory::new(MemoryConfig {
+        endianness: Endianness::Little,
+        ..Default::default()
+    });
+
+    memory.store_word(TEXT_BASE, 0x1234_5678).unwrap();
+    memory
+        .store_doubleword(TEXT_BASE + 8, 0x0011_2233_4455_6677)
+        .unwrap();
+
+    assert_eq!(memory.load_byte(TEXT_BASE).unwrap(), 0x78);
+    assert_eq!(memory.load_half(TEXT_BASE + 2).unwrap(), 0x1234);
+    assert_eq!(memory.load_word(TEXT_BASE + 8).unwrap(), 0x4455_6677);
+    assert_eq!(
+        memory.load_doubleword(TEXT_BASE + 8).unwrap(),
+        0x0011_2233_4455_6677
+    );
+}
+
+/// A device that counts its reads and remembers the last value written.
+#[derive(Default)]
+struct Counter {
+    reads: u64,
+    written: Rc<Cell<u64>>,
+}
+
+impl Device for Counter {
+    fn read(&mut self, offset: u64, _size: u64) -> Result<u64, ErrorKind> {
+        if offset != 0 {
+            return Err(ErrorKind::BusError { address: offset });
+        }
+        self.reads += 1;
+        Ok(self.reads)
+    }
+
+    fn write(&mut self, _offset: u64, _size: u64, value: u64) -> Result<(), ErrorKind> {
+        self.written.set(value);
+        Ok(())
+    }
+}
+
+#[test]
+fn devices_claim_address_ranges() {
+    let mut memory = Memory::default();
+    let counter = Counter::default();
+    let written = counter.written.clone();
+
+    memory.store_word(0xffff_0010, 0xdead_beef).unwrap();
+    memory.attach(0xffff_0010, 0xffff_0018, Box::new(counter));
+
+    assert_eq!(memory.load_word(0xffff_0010).unwrap(), 1);
+    assert_eq!(memory.load_word(0xffff_ffff_ffff_0010).unwrap(), 2);
+    memory.store_half(0xffff_0014, 0x1234).unwrap();
+    assert_eq!(written.get(), 0x1234);
+    // RAM still answers outside the claimed range.
+    assert_eq!(memory.load_word(0xffff_0018).unwrap(), 0);
+}
+
+#[test]
+fn device_bus_errors_report_full_address() {
+    let mut memory = Memory::default();
+    memory.attach(0xffff_0010, 0xffff_0014, Box::new(Counter::default()));
+
+    assert_eq!(
+        memory.load_byte(0xffff_0012),
+        Err(ErrorKind::BusError {
+            address: 0xffff_0012
+        })
+    );
+    // Only half of this doubleword is claimed by the device.
+    assert_eq!(
+        memory.load_doubleword(0xffff_0010),
+        Err(ErrorKind::BusError {
+            address: 0xffff_0010
+        })
+    );
+}
+
+#[test]
+fn accesses_past_configured_size_are_address_errors() {
+    let mut memory = Memory::new(MemoryConfig {
+        size: Some(0x1_0000),
+        ..Default::default()
+    });
+    memory.attach(0xffff_0010, 0xffff_0018, Box::new(Counter::default()));
+
+    assert_eq!(
+        memory.store_word(0x1_0000, 1),
+        Err(ErrorKind::AddressErrorStore { address: 0x1_0000 })
+    );
+    assert_eq!(
+        memory.load_byte(0x1_0000),
+        Err(ErrorKind::AddressErrorLoad { address: 0x1_0000 })
+    );
+    memory.store_word(0xfffc, 1).unwrap();
+    // Devices still answer above the end of RAM.
+    assert_eq!(memory.load_word(0xffff_0010).unwrap(), 1);
+}
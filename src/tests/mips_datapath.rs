@@ -1,17 +1,1409 @@
 use crate::datapath::Datapath;
-use crate::mips::datapath::MipsDatapath;
+use crate::mips::console::{Console, CONSOLE_BASE, CONSOLE_END};
+use crate::mips::coprocessor0::{STATUS_EXL, STATUS_IE};
+use crate::mips::datapath::{ExecutionState, MipsDatapath};
+use crate::mips::error::{ErrorKind, ExecutionError};
+use crate::mips::memory::{Endianness, Memory, MemoryConfig, SegmentKind, TEXT_BASE};
 use crate::mips::registers::RegisterType;
+use crate::mips::syscall::{SharedBuffer, SyscallMode};
+use std::io::{BufRead, Cursor};
+
+/// A datapath whose text segment starts at address 0, which keeps
+/// hand-encoded jump targets and expected addresses small.
+fn test_datapath() -> MipsDatapath {
+    MipsDatapath::new(MemoryConfig {
+        text_base: 0,
+        ..Default::default()
+    })
+}
+
+/// Store consecutive instruction words starting at address 0.
+fn load_instructions(datapath: &mut MipsDatapath, instructions: &[u32]) {
+    for (i, instruction) in instructions.iter().enumerate() {
+        datapath
+            .memory
+            .store_word(i as u64 * 4, *instruction)
+            .unwrap();
+    }
+}
 
 #[allow(clippy::unusual_byte_groupings)]
 #[test]
 fn double_register() {
-    let mut datapath = MipsDatapath::default();
+    let mut datapath = test_datapath();
 
     let instruction: u32 = 0b000000_01001_01001_01001_00000_100000;
-    datapath.memory.store_word(0, instruction);
+    datapath.memory.store_word(0, instruction).unwrap();
     datapath.registers[RegisterType::T1] = 5;
 
-    datapath.execute_instruction();
+    datapath.execute_instruction().unwrap();
 
     assert_eq!(datapath.registers.gpr[9], 10);
 }
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn add_immediate_negative() {
+    let mut datapath = test_datapath();
+
+    // addi $t0, $t1, -3
+    let instruction: u32 = 0b001000_01001_01000_1111111111111101;
+    datapath.memory.store_word(0, instruction).unwrap();
+    datapath.registers[RegisterType::T1] = 5;
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[8], 2);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn logical_immediates_zero_extend() {
+    let mut datapath = test_datapath();
+
+    // ori $t0, $zero, 0xffff
+    let instruction: u32 = 0b001101_00000_01000_1111111111111111;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    // andi $t1, $t0, 0x8001
+    let instruction: u32 = 0b001100_01000_01001_1000000000000001;
+    datapath.memory.store_word(4, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[8], 0xffff);
+    assert_eq!(datapath.registers.gpr[9], 0x8001);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn load_upper_immediate() {
+    let mut datapath = test_datapath();
+
+    // lui $t0, 0x1234
+    let instruction: u32 = 0b001111_00000_01000_0001001000110100;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[8], 0x1234_0000);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn set_on_less_than_immediate() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T1] = -4i64 as u64;
+
+    // slti $t0, $t1, 1
+    let instruction: u32 = 0b001010_01001_01000_0000000000000001;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    // sltiu $t2, $t1, 1
+    let instruction: u32 = 0b001011_01001_01010_0000000000000001;
+    datapath.memory.store_word(4, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[8], 1);
+    assert_eq!(datapath.registers.gpr[10], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn immediate_write_to_zero_is_ignored() {
+    let mut datapath = test_datapath();
+
+    // addi $zero, $zero, 7
+    let instruction: u32 = 0b001000_00000_00000_0000000000000111;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[0], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn store_and_load_word() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x100;
+    datapath.registers[RegisterType::T1] = 0x1234_5678;
+
+    // sw $t1, 4($t0)
+    let instruction: u32 = 0b101011_01000_01001_0000000000000100;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    // lw $t2, 4($t0)
+    let instruction: u32 = 0b100011_01000_01010_0000000000000100;
+    datapath.memory.store_word(4, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.memory.load_word(0x104).unwrap(), 0x1234_5678);
+    assert_eq!(datapath.registers.gpr[10], 0x1234_5678);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn load_byte_sign_and_zero_extend() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x100;
+    datapath.memory.store_byte(0xff, 0x80).unwrap();
+
+    // lb $t1, -1($t0)
+    let instruction: u32 = 0b100000_01000_01001_1111111111111111;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    // lbu $t2, -1($t0)
+    let instruction: u32 = 0b100100_01000_01010_1111111111111111;
+    datapath.memory.store_word(4, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[9], -128i64 as u64);
+    assert_eq!(datapath.registers.gpr[10], 0x80);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn store_half_load_half() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x200;
+    datapath.registers[RegisterType::T1] = 0xdead_beef;
+
+    // sh $t1, 2($t0)
+    let instruction: u32 = 0b101001_01000_01001_0000000000000010;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    // lh $t2, 2($t0)
+    let instruction: u32 = 0b100001_01000_01010_0000000000000010;
+    datapath.memory.store_word(4, instruction).unwrap();
+
+    // lhu $t3, 2($t0)
+    let instruction: u32 = 0b100101_01000_01011_0000000000000010;
+    datapath.memory.store_word(8, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.memory.load_word(0x200).unwrap(), 0x0000_beef);
+    assert_eq!(datapath.registers.gpr[10], 0xffff_ffff_ffff_beef);
+    assert_eq!(datapath.registers.gpr[11], 0xbeef);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn branch_loop() {
+    let mut datapath = test_datapath();
+
+    let instructions: [u32; 4] = [
+        0b001000_00000_01000_0000000000000011,   // addi $t0, $zero, 3
+        0b000000_01001_01000_01001_00000_100000, // add $t1, $t1, $t0
+        0b001000_01000_01000_1111111111111111,   // addi $t0, $t0, -1
+        0b000101_01000_00000_1111111111111101,   // bne $t0, $zero, -3
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    while datapath.registers.pc != 16 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers.gpr[9], 6);
+    assert_eq!(datapath.registers.gpr[8], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn branch_not_taken() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 1;
+
+    // blez $t0, 8
+    let instruction: u32 = 0b000110_01000_00000_0000000000001000;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 4);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn branch_and_link() {
+    let mut datapath = test_datapath();
+
+    // bgezal $zero, 2
+    let instruction: u32 = 0b000001_00000_10001_0000000000000010;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 12);
+    assert_eq!(datapath.registers[RegisterType::Ra], 4);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn jump_and_link_then_return() {
+    let mut datapath = test_datapath();
+
+    let instructions: [u32; 5] = [
+        0b000011_00000000000000000000000011,     // jal 12
+        0b001000_00000_01001_0000000000000001,   // addi $t1, $zero, 1
+        0b000010_00000000000000000000000100,     // j 16
+        0b000000_11111_00000_00000_00000_001000, // jr $ra
+        0b000000_00000_00000_00000_00000_100000, // add $zero, $zero, $zero
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    // jal
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 12);
+    assert_eq!(datapath.registers[RegisterType::Ra], 4);
+
+    // jr
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 4);
+
+    // addi, j
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 16);
+    assert_eq!(datapath.registers.gpr[9], 1);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn jump_and_link_register() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 8;
+
+    // jalr $t1, $t0
+    let instruction: u32 = 0b000000_01000_00000_01001_00000_001001;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 8);
+    assert_eq!(datapath.registers.gpr[9], 4);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn nop_does_nothing() {
+    let mut datapath = test_datapath();
+
+    // sll $zero, $zero, 0
+    datapath.memory.store_word(0, 0).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr, test_datapath().registers.gpr);
+    assert_eq!(datapath.registers.pc, 4);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn nor_registers() {
+    let mut datapath = test_datapath();
+
+    // nor $t0, $t1, $zero
+    let instruction: u32 = 0b000000_01001_00000_01000_00000_100111;
+    datapath.memory.store_word(0, instruction).unwrap();
+    datapath.registers[RegisterType::T1] = 0x0F0F_0000;
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[8], 0xFFFF_FFFF_F0F0_FFFF);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn shift_by_constant() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0xffff_ffff_8000_0010;
+
+    let instructions: [u32; 3] = [
+        0b000000_00000_01000_01001_00100_000000, // sll $t1, $t0, 4
+        0b000000_00000_01000_01010_00100_000010, // srl $t2, $t0, 4
+        0b000000_00000_01000_01011_00100_000011, // sra $t3, $t0, 4
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[9], 0x100);
+    assert_eq!(datapath.registers.gpr[10], 0x0800_0001);
+    assert_eq!(datapath.registers.gpr[11], 0xffff_ffff_f800_0001);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn shift_by_register() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 1;
+    // Only the low five bits of the shift amount are used.
+    datapath.registers[RegisterType::T1] = 35;
+
+    // sllv $t2, $t0, $t1
+    let instruction: u32 = 0b000000_01001_01000_01010_00000_000100;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[10], 8);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn multiply_signed_and_unsigned() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = -2i64 as u64;
+    datapath.registers[RegisterType::T1] = 3;
+
+    let instructions: [u32; 4] = [
+        0b000000_01000_01001_00000_00000_011000, // mult $t0, $t1
+        0b000000_00000_00000_01010_00000_010000, // mfhi $t2
+        0b000000_00000_00000_01011_00000_010010, // mflo $t3
+        0b000000_01000_01001_00000_00000_011001, // multu $t0, $t1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[10], -1i64 as u64);
+    assert_eq!(datapath.registers.gpr[11], -6i64 as u64);
+
+    datapath.execute_instruction().unwrap();
+
+    // 0xfffffffe * 3 = 0x2_ffff_fffa
+    assert_eq!(datapath.registers["hi"], 2);
+    assert_eq!(datapath.registers["lo"], -6i64 as u64);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn divide_signed() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = -7i64 as u64;
+    datapath.registers[RegisterType::T1] = 2;
+
+    // div $t0, $t1
+    let instruction: u32 = 0b000000_01000_01001_00000_00000_011010;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers[RegisterType::Lo], -3i64 as u64);
+    assert_eq!(datapath.registers[RegisterType::Hi], -1i64 as u64);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn divide_by_zero_does_not_trap() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 7;
+    datapath.registers[RegisterType::T1] = 5;
+
+    let instructions: [u32; 3] = [
+        0b000000_01000_00000_00000_00000_010001, // mthi $t0
+        0b000000_01001_00000_00000_00000_010011, // mtlo $t1
+        0b000000_01000_00000_00000_00000_011011, // divu $t0, $zero
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 12);
+    assert_eq!(datapath.registers.hi, 7);
+    assert_eq!(datapath.registers.lo, 5);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn unsigned_arithmetic_wraps() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x7fff_ffff;
+
+    let instructions: [u32; 2] = [
+        0b001001_01000_01001_0000000000000001,   // addiu $t1, $t0, 1
+        0b000000_01001_01000_01010_00000_100011, // subu $t2, $t1, $t0
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.gpr[9], 0xffff_ffff_8000_0000);
+    assert_eq!(datapath.registers.gpr[10], 1);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn signed_add_overflow() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x7fff_ffff;
+
+    // addi $t1, $t0, 1
+    let instruction: u32 = 0b001000_01000_01001_0000000000000001;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::IntegerOverflow);
+    assert_eq!(datapath.registers.gpr[9], 0);
+    assert_eq!(datapath.registers.pc, 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn reserved_instruction_error() {
+    let mut datapath = test_datapath();
+
+    let instruction: u32 = 0b111111_00000_00000_0000000000000000;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(
+        error,
+        ExecutionError {
+            kind: ErrorKind::ReservedInstruction,
+            pc: 0,
+            instruction,
+            segment: None,
+        }
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn address_errors_name_their_segment() {
+    let mut datapath = MipsDatapath::default();
+    datapath.registers[RegisterType::T0] = 0x1001_0002;
+
+    // sw $t1, 0($t0)
+    let instruction: u32 = 0b101011_01000_01001_0000000000000000;
+    datapath.memory.store_word(TEXT_BASE, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(error.segment, Some(SegmentKind::Data));
+    assert_eq!(
+        error.to_string(),
+        "address error on store to data segment at 0x10010002 \
+         (pc 0x00400000, instruction 0xad090000)"
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn load_past_end_of_memory() {
+    let mut datapath = test_datapath();
+    datapath.memory = Memory::new(MemoryConfig {
+        size: Some(0x1_0000),
+        ..Default::default()
+    });
+    datapath.registers[RegisterType::T0] = 0x1_0000;
+
+    // lw $t1, 0($t0)
+    let instruction: u32 = 0b100011_01000_01001_0000000000000000;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(
+        error.kind,
+        ErrorKind::AddressErrorLoad { address: 0x1_0000 }
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_without_handler_is_an_error() {
+    let mut datapath = test_datapath();
+    datapath.syscall_mode = SyscallMode::Exception;
+
+    // syscall
+    let instruction: u32 = 0b000000_00000_00000_00000_00000_001100;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Syscall);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn exception_transfers_to_handler_and_returns() {
+    let mut datapath = test_datapath();
+    datapath.exception_vector = Some(0x100);
+    datapath.registers[RegisterType::T0] = 0x7fff_ffff;
+
+    let instructions: [u32; 2] = [
+        0b001000_01000_01001_0000000000000001, // addi $t1, $t0, 1
+        0b001000_00000_01010_0000000000000001, // addi $t2, $zero, 1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    let handler: [u32; 4] = [
+        0b010000_00000_01011_01110_00000000000, // mfc0 $t3, $14
+        0b001001_01011_01011_0000000000000100,  // addiu $t3, $t3, 4
+        0b010000_00100_01011_01110_00000000000, // mtc0 $t3, $14
+        0b010000_1_0000000000000000000_011000,  // eret
+    ];
+    for (i, instruction) in handler.iter().enumerate() {
+        datapath
+            .memory
+            .store_word(0x100 + i as u64 * 4, *instruction)
+            .unwrap();
+    }
+
+    // addi overflows
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 0x100);
+    assert_eq!(datapath.registers.gpr[9], 0);
+    assert_eq!(datapath.coprocessor0.epc, 0);
+    assert_eq!(datapath.coprocessor0.exception_code(), 12);
+    assert_ne!(datapath.coprocessor0.status & STATUS_EXL, 0);
+
+    // Skip the faulting instruction and return.
+    for _ in 0..4 {
+        datapath.execute_instruction().unwrap();
+    }
+    assert_eq!(datapath.registers.pc, 4);
+    assert_eq!(datapath.coprocessor0.status & STATUS_EXL, 0);
+
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.gpr[10], 1);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn address_error_sets_bad_vaddr() {
+    let mut datapath = test_datapath();
+    datapath.exception_vector = Some(0x100);
+    datapath.registers[RegisterType::T0] = 0x202;
+
+    // jr $t0
+    let instruction: u32 = 0b000000_01000_00000_00000_00000_001000;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 0x100);
+    assert_eq!(datapath.coprocessor0.epc, 0x202);
+    assert_eq!(datapath.coprocessor0.bad_vaddr, 0x202);
+    assert_eq!(datapath.coprocessor0.exception_code(), 4);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_print_and_exit() {
+    let mut datapath = test_datapath();
+    let stdout = SharedBuffer::default();
+    datapath.syscalls.stdout = Box::new(stdout.clone());
+
+    for (i, byte) in b"hi\n\0".iter().enumerate() {
+        datapath.memory.store_byte(0x200 + i as u64, *byte).unwrap();
+    }
+
+    let instructions: [u32; 9] = [
+        0b001000_00000_00010_0000000000000001,   // addi $v0, $zero, 1
+        0b001000_00000_00100_1111111111111011,   // addi $a0, $zero, -5
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b001000_00000_00010_0000000000000100,   // addi $v0, $zero, 4
+        0b001000_00000_00100_0000001000000000,   // addi $a0, $zero, 0x200
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b001000_00000_00010_0000000000001010,   // addi $v0, $zero, 10
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b001000_00000_01000_0000000000000001,   // addi $t0, $zero, 1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    while !datapath.is_halted() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    // Further instructions do not run once the program has exited.
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(stdout.contents(), "-5hi\n");
+    assert_eq!(datapath.state, ExecutionState::Exited(0));
+    assert_eq!(datapath.registers.gpr[8], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_read_int_and_exit2() {
+    let mut datapath = test_datapath();
+    datapath.syscalls.stdin = Box::new(Cursor::new("42\n"));
+
+    let instructions: [u32; 4] = [
+        0b001000_00000_00010_0000000000000101,   // addi $v0, $zero, 5
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b000000_00010_00000_00100_00000_100000, // add $a0, $v0, $zero
+        0b001000_00000_00010_0000000000010001,   // addi $v0, $zero, 17
+    ];
+    load_instructions(&mut datapath, &instructions);
+    datapath
+        .memory
+        .store_word(16, 0b000000_00000_00000_00000_00000_001100)
+        .unwrap();
+
+    while !datapath.is_halted() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.state, ExecutionState::Exited(42));
+    assert_eq!(datapath.state.to_string(), "program exited with code 42");
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_negative_length_is_refused() {
+    let mut datapath = test_datapath();
+    let stdout = SharedBuffer::default();
+    datapath.syscalls.stdout = Box::new(stdout.clone());
+    datapath.syscalls.stdin = Box::new(Cursor::new("input"));
+
+    let instructions: [u32; 9] = [
+        0b001000_00000_00010_0000000000001111,   // addi $v0, $zero, 15
+        0b001000_00000_00100_0000000000000001,   // addi $a0, $zero, 1
+        0b001000_00000_00101_0000001000000000,   // addi $a1, $zero, 0x200
+        0b001000_00000_00110_1111111111111111,   // addi $a2, $zero, -1
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b000000_00010_00000_01000_00000_100000, // add $t0, $v0, $zero
+        0b001000_00000_00010_0000000000001110,   // addi $v0, $zero, 14
+        0b001000_00000_00100_0000000000000000,   // addi $a0, $zero, 0
+        0b000000_00000_00000_00000_00000_001100, // syscall
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers.gpr[8], u64::MAX);
+    assert_eq!(datapath.registers.gpr[2], u64::MAX);
+    assert_eq!(stdout.contents(), "");
+    assert_eq!(datapath.memory.load_word(0x200).unwrap(), 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_read_faults_before_consuming_input() {
+    let mut datapath = test_datapath();
+    datapath.memory = Memory::new(MemoryConfig {
+        text_base: 0,
+        size: Some(0x1_0000),
+        ..Default::default()
+    });
+    datapath.syscalls.stdin = Box::new(Cursor::new("input\n"));
+
+    let instructions: [u32; 5] = [
+        0b001111_00000_00101_0000000000000010,   // lui $a1, 2
+        0b001000_00000_00010_0000000000001110,   // addi $v0, $zero, 14
+        0b001000_00000_00100_0000000000000000,   // addi $a0, $zero, 0
+        0b001000_00000_00110_0111111111111111,   // addi $a2, $zero, 0x7fff
+        0b000000_00000_00000_00000_00000_001100, // syscall
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..4 {
+        datapath.execute_instruction().unwrap();
+    }
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(
+        error.kind,
+        ErrorKind::AddressErrorStore { address: 0x2_0000 }
+    );
+    let mut line = String::new();
+    datapath.syscalls.stdin.read_line(&mut line).unwrap();
+    assert_eq!(line, "input\n");
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn syscall_write_longer_than_a_chunk() {
+    let mut datapath = test_datapath();
+    let stdout = SharedBuffer::default();
+    datapath.syscalls.stdout = Box::new(stdout.clone());
+    datapath.memory.store_byte(0x100 + 4999, b'!').unwrap();
+
+    let instructions: [u32; 5] = [
+        0b001000_00000_00010_0000000000001111,   // addi $v0, $zero, 15
+        0b001000_00000_00100_0000000000000001,   // addi $a0, $zero, 1
+        0b001000_00000_00101_0000000100000000,   // addi $a1, $zero, 0x100
+        0b001000_00000_00110_0001001110001000,   // addi $a2, $zero, 5000
+        0b000000_00000_00000_00000_00000_001100, // syscall
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers.gpr[2], 5000);
+    let output = stdout.contents();
+    assert_eq!(output.len(), 5000);
+    assert!(output.ends_with('!'));
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn single_precision_arithmetic() {
+    let mut datapath = test_datapath();
+    datapath.memory.store_word(0x200, 1.5f32.to_bits()).unwrap();
+    datapath
+        .memory
+        .store_word(0x204, 2.25f32.to_bits())
+        .unwrap();
+
+    let instructions: [u32; 5] = [
+        0b110001_00000_00000_0000001000000000, // lwc1 $f0, 0x200($zero)
+        0b110001_00000_00001_0000001000000100, // lwc1 $f1, 0x204($zero)
+        0b010001_10000_00001_00000_00010_000000, // add.s $f2, $f0, $f1
+        0b111001_00000_00010_0000001000001000, // swc1 $f2, 0x208($zero)
+        0b010001_00000_01000_00010_00000_000000, // mfc1 $t0, $f2
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(
+        datapath.registers[RegisterType::F2],
+        3.75f32.to_bits() as u64
+    );
+    assert_eq!(datapath.memory.load_word(0x208).unwrap(), 3.75f32.to_bits());
+    assert_eq!(datapath.registers.gpr[8], 3.75f32.to_bits() as u64);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn double_precision_arithmetic() {
+    let mut datapath = test_datapath();
+    datapath
+        .memory
+        .store_doubleword(0x200, 16.0f64.to_bits())
+        .unwrap();
+
+    let instructions: [u32; 4] = [
+        0b110101_00000_00100_0000001000000000, // ldc1 $f4, 0x200($zero)
+        0b010001_10001_00000_00100_00110_000100, // sqrt.d $f6, $f4
+        0b010001_10001_00000_00110_01000_000111, // neg.d $f8, $f6
+        0b111101_00000_01000_0000001000001000, // sdc1 $f8, 0x208($zero)
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::F6], 4.0f64.to_bits());
+    assert_eq!(
+        datapath.memory.load_doubleword(0x208).unwrap(),
+        (-4.0f64).to_bits()
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn move_to_coprocessor_1() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 2.0f32.to_bits() as u64;
+
+    let instructions: [u32; 2] = [
+        0b010001_00100_01000_00000_00000_000000, // mtc1 $t0, $f0
+        0b010001_10000_00000_00000_00001_000010, // mul.s $f1, $f0, $f0
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers["f1"], 4.0f32.to_bits() as u64);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn compare_and_branch_on_fpu_condition() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 1.0f32.to_bits() as u64;
+    datapath.registers[RegisterType::F1] = 2.0f32.to_bits() as u64;
+
+    let instructions: [u32; 4] = [
+        0b010001_10000_00001_00000_00000_111100, // c.lt.s $f0, $f1
+        0b010001_01000_00001_0000000000000001,   // bc1t 1
+        0b001000_00000_01000_0000000000000001,   // addi $t0, $zero, 1
+        0b001000_00000_01001_0000000000000001,   // addi $t1, $zero, 1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    while datapath.registers.pc != 16 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert!(datapath.registers.get_condition_code(0));
+    assert_eq!(datapath.registers.fcsr, 1 << 23);
+    assert_eq!(datapath.registers.gpr[8], 0);
+    assert_eq!(datapath.registers.gpr[9], 1);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn branch_likely_on_fpu_condition_is_reserved() {
+    let mut datapath = test_datapath();
+
+    let instructions: [u32; 2] = [
+        0b010001_01000_00011_0000000000000001, // bc1tl 1
+        0b010001_01000_00010_0000000000000001, // bc1fl 1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    let error = datapath.execute_instruction().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ReservedInstruction);
+
+    datapath.registers.pc = 4;
+    let error = datapath.execute_instruction().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ReservedInstruction);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn compare_unordered_with_condition_code_selector() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F2] = f64::NAN.to_bits();
+    datapath.registers[RegisterType::F4] = 1.0f64.to_bits();
+
+    let instructions: [u32; 3] = [
+        0b010001_10001_00100_00010_01100_110010, // c.eq.d $fcc3, $f2, $f4
+        0b010001_10001_00100_00010_01000_110011, // c.ueq.d $fcc2, $f2, $f4
+        0b010001_01000_01100_0000000000000011,   // bc1f $fcc3, 3
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert!(!datapath.registers.get_condition_code(3));
+    assert!(datapath.registers.get_condition_code(2));
+    assert_eq!(datapath.registers.fcsr, 1 << 26);
+    assert_eq!(datapath.registers.pc, 24);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn quiet_compare_with_signalling_nan_is_invalid() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 0x7F80_0001;
+    datapath.registers[RegisterType::F1] = 1.0f32.to_bits() as u64;
+    datapath.registers[RegisterType::F2] = f32::NAN.to_bits() as u64;
+
+    let instructions: [u32; 2] = [
+        0b010001_10000_00010_00001_00000_110010, // c.eq.s $f1, $f2
+        0b010001_10000_00001_00000_00000_110010, // c.eq.s $f0, $f1
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    // A quiet NaN is only unordered.
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.fcsr, 0);
+
+    datapath.execute_instruction().unwrap();
+    assert!(!datapath.registers.get_condition_code(0));
+    assert_eq!(datapath.registers.fcsr, 1 << 6 | 1 << 16);
+
+    datapath.registers.fcsr = 1 << 11;
+    datapath.registers.pc = 4;
+    let error = datapath.execute_instruction().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::FloatingPointException);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn convert_to_word_with_rounding_modes() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 2.5f32.to_bits() as u64;
+
+    let instructions: [u32; 6] = [
+        0b001101_00000_01000_0000000000000010,   // ori $t0, $zero, 2
+        0b010001_00110_01000_11111_00000_000000, // ctc1 $t0, $31
+        0b010001_10000_00000_00000_00010_100100, // cvt.w.s $f2, $f0
+        0b010001_10000_00000_00000_00100_001111, // floor.w.s $f4, $f0
+        0b010001_10000_00000_00000_00110_001100, // round.w.s $f6, $f0
+        0b010001_00010_01001_11111_00000_000000, // cfc1 $t1, $31
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::F2], 3);
+    assert_eq!(datapath.registers[RegisterType::F4], 2);
+    assert_eq!(datapath.registers[RegisterType::F6], 2);
+    // Round toward positive, with the inexact flag and cause set.
+    assert_eq!(datapath.registers[RegisterType::T1], 2 | 1 << 2 | 1 << 12);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn divide_with_directed_rounding() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 1.0f32.to_bits() as u64;
+    datapath.registers[RegisterType::F2] = 3.0f32.to_bits() as u64;
+
+    let instructions: [u32; 6] = [
+        0b001101_00000_01000_0000000000000010,   // ori $t0, $zero, 2
+        0b010001_00110_01000_11111_00000_000000, // ctc1 $t0, $31
+        0b010001_10000_00010_00000_00100_000011, // div.s $f4, $f0, $f2
+        0b001101_00000_01000_0000000000000011,   // ori $t0, $zero, 3
+        0b010001_00110_01000_11111_00000_000000, // ctc1 $t0, $31
+        0b010001_10000_00010_00000_00110_000011, // div.s $f6, $f0, $f2
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::F4], 0x3EAA_AAAB);
+    assert_eq!(datapath.registers[RegisterType::F6], 0x3EAA_AAAA);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn exact_zero_sum_rounding_toward_negative() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 1.5f32.to_bits() as u64;
+    datapath.registers[RegisterType::F1] = (-1.5f32).to_bits() as u64;
+    datapath.registers[RegisterType::F6] = 2.0f64.to_bits();
+
+    let instructions: [u32; 5] = [
+        0b001101_00000_01000_0000000000000011,   // ori $t0, $zero, 3
+        0b010001_00110_01000_11111_00000_000000, // ctc1 $t0, $31
+        0b010001_10000_00001_00000_00010_000000, // add.s $f2, $f0, $f1
+        0b010001_10001_00110_00110_00100_000001, // sub.d $f4, $f6, $f6
+        0b010001_10000_01010_01010_01000_000000, // add.s $f8, $f10, $f10
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::F2], 0x8000_0000);
+    assert_eq!(datapath.registers[RegisterType::F4], 0x8000_0000_0000_0000);
+    // Two +0 operands still sum to +0.
+    assert_eq!(datapath.registers[RegisterType::F8], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn conditional_floating_point_moves() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 1.5f64.to_bits();
+
+    let instructions: [u32; 4] = [
+        0b010001_10001_00000_00000_00000_110010, // c.eq.d $f0, $f0
+        0b010001_10001_00001_00000_00010_010001, // movt.d $f2, $f0, $fcc0
+        0b010001_10001_00000_00000_00100_010001, // movf.d $f4, $f0, $fcc0
+        0b010001_10001_00000_00000_00110_010010, // movz.d $f6, $f0, $zero
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::F2], 1.5f64.to_bits());
+    assert_eq!(datapath.registers[RegisterType::F4], 0);
+    assert_eq!(datapath.registers[RegisterType::F6], 1.5f64.to_bits());
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn enabled_floating_point_exception() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 1.0f64.to_bits();
+    // Enable the divide-by-zero exception.
+    datapath.registers.fcsr = 1 << 10;
+
+    let instructions: [u32; 1] = [
+        0b010001_10001_00010_00000_00100_000011, // div.d $f4, $f0, $f2
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::FloatingPointException);
+    assert_eq!(datapath.registers.fcsr, 1 << 10 | 1 << 15);
+    assert_eq!(datapath.registers[RegisterType::F4], 0);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn signalling_nan_operand_is_invalid() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::F0] = 0x7F80_0001;
+    datapath.registers[RegisterType::F1] = 1.0f32.to_bits() as u64;
+
+    let instructions: [u32; 2] = [
+        0b010001_10000_00001_00000_00010_000000, // add.s $f2, $f0, $f1
+        0b010001_10000_00000_00000_00100_100001, // cvt.d.s $f4, $f0
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    assert!(f32::from_bits(datapath.registers[RegisterType::F2] as u32).is_nan());
+    assert_eq!(datapath.registers.fcsr, 1 << 6 | 1 << 16);
+
+    datapath.registers.fcsr = 0;
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.fcsr, 1 << 6 | 1 << 16);
+
+    // With the invalid exception enabled, the add traps.
+    datapath.registers.fcsr = 1 << 11;
+    datapath.registers.pc = 0;
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::FloatingPointException);
+    assert_eq!(datapath.registers.fcsr, 1 << 11 | 1 << 16);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn conventional_initial_registers() {
+    let mut datapath = MipsDatapath::default();
+
+    // sw $ra, -4($sp)
+    let instruction: u32 = 0b101011_11101_11111_1111111111111100;
+    datapath.memory.store_word(TEXT_BASE, instruction).unwrap();
+    datapath.registers[RegisterType::Ra] = 0xbeef;
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, TEXT_BASE + 4);
+    assert_eq!(datapath.registers[RegisterType::Gp], 0x1000_8000);
+    assert_eq!(datapath.registers[RegisterType::Sp], 0x7fff_fffc);
+    assert_eq!(datapath.memory.load_word(0x7fff_fff8).unwrap(), 0xbeef);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn sbrk_grows_heap_segment() {
+    let mut datapath = test_datapath();
+
+    let instructions: [u32; 3] = [
+        0b001000_00000_00010_0000000000001001,   // addi $v0, $zero, 9
+        0b001000_00000_00100_0000000000001010,   // addi $a0, $zero, 10
+        0b000000_00000_00000_00000_00000_001100, // syscall
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::V0], 0x1004_0000);
+    let segment = datapath.memory.segment_at(0x1004_0008).unwrap();
+    assert_eq!(segment.kind, SegmentKind::Heap);
+    assert_eq!(segment.end, 0x1004_000c);
+    assert_eq!(
+        datapath.memory.segment_at(0x1004_000c).unwrap().kind,
+        SegmentKind::Stack
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn sbrk_refuses_to_pass_stack_pointer() {
+    let mut datapath = test_datapath();
+
+    let instructions: [u32; 6] = [
+        0b001111_00000_00100_0111000000000000,   // lui $a0, 0x7000
+        0b001000_00000_00010_0000000000001001,   // addi $v0, $zero, 9
+        0b000000_00000_00000_00000_00000_001100, // syscall
+        0b001000_00000_00100_0000000000010000,   // addi $a0, $zero, 16
+        0b001000_00000_00010_0000000000001001,   // addi $v0, $zero, 9
+        0b000000_00000_00000_00000_00000_001100, // syscall
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    for _ in 0..3 {
+        datapath.execute_instruction().unwrap();
+    }
+    assert_eq!(datapath.registers[RegisterType::V0], u64::MAX);
+    assert_eq!(
+        datapath.memory.segment_at(0x1004_0000).unwrap().kind,
+        SegmentKind::Stack
+    );
+
+    for _ in 3..instructions.len() {
+        datapath.execute_instruction().unwrap();
+    }
+    assert_eq!(datapath.registers[RegisterType::V0], 0x1004_0000);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn misaligned_load_is_address_error() {
+    let mut datapath = test_datapath();
+    datapath.registers[RegisterType::T0] = 0x1001_0002;
+
+    // lw $t1, 0($t0)
+    let instruction: u32 = 0b100011_01000_01001_0000000000000000;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    let error = datapath.execute_instruction().unwrap_err();
+
+    assert_eq!(
+        error.kind,
+        ErrorKind::AddressErrorLoad {
+            address: 0x1001_0002
+        }
+    );
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn misaligned_store_raises_exception() {
+    let mut datapath = test_datapath();
+    datapath.exception_vector = Some(0x100);
+    datapath.registers[RegisterType::T0] = 0x1001_0000;
+
+    // sh $t1, 3($t0)
+    let instruction: u32 = 0b101001_01000_01001_0000000000000011;
+    datapath.memory.store_word(0, instruction).unwrap();
+
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 0x100);
+    assert_eq!(datapath.coprocessor0.epc, 0);
+    assert_eq!(datapath.coprocessor0.exception_code(), 5);
+    assert_eq!(datapath.coprocessor0.bad_vaddr, 0x1001_0003);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn little_endian_execution() {
+    let mut datapath = MipsDatapath::new(MemoryConfig {
+        text_base: 0,
+        endianness: Endianness::Little,
+        ..Default::default()
+    });
+    datapath.registers[RegisterType::T0] = 0x1001_0000;
+    datapath.registers[RegisterType::T1] = 0x1122_3344;
+
+    let instructions: [u32; 2] = [
+        0b101011_01000_01001_0000000000000000, // sw $t1, 0($t0)
+        0b100000_01000_01010_0000000000000000, // lb $t2, 0($t0)
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.memory.load_byte(0).unwrap(), 0b00000000);
+    assert_eq!(datapath.memory.load_byte(3).unwrap(), 0b10101101);
+    assert_eq!(datapath.registers[RegisterType::T2], 0x44);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn console_polling_echo() {
+    let mut datapath = test_datapath();
+    let console = Console::default();
+    datapath
+        .memory
+        .attach(CONSOLE_BASE, CONSOLE_END, Box::new(console.clone()));
+
+    let instructions: [u32; 6] = [
+        0b001111_00000_01000_1111111111111111, // lui $t0, 0xffff
+        0b100011_01000_01001_0000000000000000, // lw $t1, 0($t0)
+        0b001100_01001_01001_0000000000000001, // andi $t1, $t1, 1
+        0b000100_01001_00000_1111111111111101, // beq $t1, $zero, -3
+        0b100011_01000_01010_0000000000000100, // lw $t2, 4($t0)
+        0b101011_01000_01010_0000000000001100, // sw $t2, 12($t0)
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    // Nothing has been typed, so the program keeps polling.
+    for _ in 0..10 {
+        datapath.execute_instruction().unwrap();
+    }
+    assert!(datapath.registers.pc < 16);
+
+    console.push_input(b"A");
+    while datapath.registers.pc != 24 {
+        datapath.execute_instruction().unwrap();
+    }
+
+    assert_eq!(datapath.registers[RegisterType::T2], b'A' as u64);
+    assert_eq!(console.output(), "A");
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn console_rejects_partial_register_access() {
+    let mut datapath = test_datapath();
+    let console = Console::default();
+    datapath
+        .memory
+        .attach(CONSOLE_BASE, CONSOLE_END, Box::new(console.clone()));
+    console.push_input(b"A");
+
+    let instructions: [u32; 4] = [
+        0b001111_00000_01000_1111111111111111, // lui $t0, 0xffff
+        0b100100_01000_01001_0000000000000100, // lbu $t1, 4($t0)
+        0b101000_01000_01001_0000000000001100, // sb $t1, 12($t0)
+        0b100011_01000_01001_0000000000000100, // lw $t1, 4($t0)
+    ];
+    load_instructions(&mut datapath, &instructions);
+
+    // A big-endian byte load here would otherwise read the character from
+    // what is the register's high byte.
+    datapath.execute_instruction().unwrap();
+    let error = datapath.execute_instruction().unwrap_err();
+    assert_eq!(
+        error.kind,
+        ErrorKind::BusError {
+            address: 0xffff_ffff_ffff_0004
+        }
+    );
+
+    datapath.registers.pc = 8;
+    let error = datapath.execute_instruction().unwrap_err();
+    assert_eq!(
+        error.kind,
+        ErrorKind::BusError {
+            address: 0xffff_ffff_ffff_000c
+        }
+    );
+    assert_eq!(
+        datapath.memory.load_doubleword(CONSOLE_BASE),
+        Err(ErrorKind::BusError {
+            address: 0xffff_0000
+        })
+    );
+
+    // The character is still there for a word load.
+    datapath.registers.pc = 12;
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers[RegisterType::T1], b'A' as u64);
+    assert_eq!(console.output(), "");
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn console_keyboard_interrupt() {
+    let mut datapath = test_datapath();
+    let console = Console::default();
+    datapath
+        .memory
+        .attach(CONSOLE_BASE, CONSOLE_END, Box::new(console.clone()));
+    datapath.exception_vector = Some(0x100);
+    // Enable interrupts, unmasking the keyboard.
+    datapath.coprocessor0.status = STATUS_IE | 1 << 8;
+
+    let instructions: [u32; 4] = [
+        0b001111_00000_01000_1111111111111111, // lui $t0, 0xffff
+        0b001101_00000_01001_0000000000000010, // ori $t1, $zero, 2
+        0b101011_01000_01001_0000000000000000, // sw $t1, 0($t0)
+        0b000100_00000_00000_1111111111111111, // beq $zero, $zero, -1
+    ];
+    load_instructions(&mut datapath, &instructions);
+    // lw $t2, 4($t0)
+    let handler: u32 = 0b100011_01000_01010_0000000000000100;
+    datapath.memory.store_word(0x100, handler).unwrap();
+
+    for _ in 0..6 {
+        datapath.execute_instruction().unwrap();
+    }
+    assert_eq!(datapath.registers.pc, 12);
+
+    console.push_input(b"x");
+    datapath.execute_instruction().unwrap();
+
+    assert_eq!(datapath.registers.pc, 0x100);
+    assert_eq!(datapath.coprocessor0.epc, 12);
+    assert_eq!(datapath.coprocessor0.exception_code(), 0);
+    assert_ne!(datapath.coprocessor0.cause & 1 << 8, 0);
+
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers[RegisterType::T2], b'x' as u64);
+}
+
+#[allow(clippy::unusual_byte_groupings)]
+#[test]
+fn console_display_interrupt_returns_to_user_code() {
+    let mut datapath = test_datapath();
+    let console = Console::default();
+    datapath
+        .memory
+        .attach(CONSOLE_BASE, CONSOLE_END, Box::new(console.clone()));
+    datapath.exception_vector = Some(0x100);
+    // Enable interrupts, unmasking the display.
+    datapath.coprocessor0.status = STATUS_IE | 1 << 9;
+
+    let instructions: [u32; 7] = [
+        0b001111_00000_01000_1111111111111111, // lui $t0, 0xffff
+        0b001101_00000_01001_0000000000000010, // ori $t1, $zero, 2
+        0b101011_01000_01001_0000000000001000, // sw $t1, 8($t0)
+        0b001101_00000_01001_0000000001000001, // ori $t1, $zero, 0x41
+        0b101011_01000_01001_0000000000001100, // sw $t1, 12($t0)
+        0b001000_10000_10000_0000000000000001, // addi $s0, $s0, 1
+        0b000100_00000_00000_1111111111111110, // beq $zero, $zero, -2
+    ];
+    load_instructions(&mut datapath, &instructions);
+    let handler: [u32; 2] = [
+        0b100011_01000_01010_0000000000001000, // lw $t2, 8($t0)
+        0b010000_1_0000000000000000000_011000, // eret
+    ];
+    for (i, instruction) in handler.iter().enumerate() {
+        datapath
+            .memory
+            .store_word(0x100 + i as u64 * 4, *instruction)
+            .unwrap();
+    }
+
+    // Enabling the interrupt alone does not raise it.
+    for _ in 0..4 {
+        datapath.execute_instruction().unwrap();
+    }
+    assert_eq!(datapath.registers.pc, 16);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 0x100);
+    assert_eq!(datapath.coprocessor0.epc, 20);
+
+    datapath.execute_instruction().unwrap();
+    datapath.execute_instruction().unwrap();
+    assert_eq!(datapath.registers.pc, 20);
+
+    for _ in 0..10 {
+        datapath.execute_instruction().unwrap();
+        assert!(datapath.registers.pc < 0x100);
+    }
+    assert_eq!(datapath.registers[RegisterType::S0], 5);
+    assert_eq!(console.output(), "A");
+}